@@ -1,31 +1,7 @@
-declare namespace raw {
-    @external("io", "log")
-    function console_log(s: i32, l: i32): void
+import * as raw from "./raw"
 
-    @external("motor", "rotate")
-    function motor_rotate(left: i32): void
-    @external("motor", "move")
-    function motor_move(dist: i32): void
-
-    @external("sensors", "contact_s")
-    function sensors_contact(ret: Entity): void
-}
-
-enum EntityType { Rock, Bot, Building }
-enum Rotation { Up, Right, Down, Left }
-
-@unmanaged
-class Entity {
-    id: i64;
-    typ: u16;
-
-    isValid(): bool {
-        return this.id > 0 && this.typ >= 0
-    }
-    getType(): EntityType {
-        return changetype<EntityType>(this.typ)
-    }
-}
+export enum Contact { None, Wall, Bot }
+export enum Level { Debug, Info, Warn, Error }
 
 export namespace io {
     /** Write string to logs without encoding (ascii only) */
@@ -37,33 +13,68 @@ export namespace io {
         const s8 = String.UTF8.encode(s)
         raw.io_log(changetype<i32>(s8), changetype<i32>(s8.byteLength))
     }
+    /** Write string to logs with a level and key-value fields */
+    export function record(level: Level, s: string, fields: Map<string, string> | null = null): void {
+        let kv = ""
+        if (fields) {
+            const keys = fields.keys()
+            for (let i = 0; i < keys.length; i++) {
+                kv += keys[i] + "=" + fields.get(keys[i]) + "\n"
+            }
+        }
+        const s8 = String.UTF8.encode(s)
+        const kv8 = String.UTF8.encode(kv)
+        raw.io_record(level, changetype<i32>(s8), s8.byteLength, changetype<i32>(kv8), kv8.byteLength)
+    }
 }
 
 export namespace motor {
-    export function rotate(left: boolean): void {
-        raw.motor_rotate(left ? -1 : 1)
+    export function forward(): void {
+        raw.motor_forward()
     }
-    export function rotate_left(): void {
-        rotate(true)
+    export function left(): void {
+        raw.motor_left()
     }
-    export function rotate_right(): void {
-        rotate(false)
+    export function right(): void {
+        raw.motor_right()
     }
+}
 
-    /** Move forward of dist cells
-      * Direction depends of current rotation
-      * Actual movement is delayed */
-    export function move(dist: u16): void {
-        raw.motor_move(dist as i32)
+export namespace sensors {
+    /** Check what is blocking path just in front (depending of rotation) */
+    export function front(): Contact {
+        return changetype<Contact>(raw.sensors_contact())
+    }
+    /** Check if there is something blocking path just in front */
+    export function contact(): bool {
+        return front() != Contact.None
     }
 }
 
-export namespace sensors {
-    /** Check for entity just in front (depending of rotation)
-      * Returns entity if something is in contact */
-    export function contact(): Entity {
-        let ret = new Entity()
-        raw.sensors_contact(ret)
-        return ret
+export namespace storage {
+    /** Read value of key in program storage */
+    export function get(key: ArrayBuffer): ArrayBuffer | null {
+        let buf = new ArrayBuffer(64)
+        while (true) {
+            const len = raw.storage_get(changetype<i32>(key), key.byteLength, changetype<i32>(buf), buf.byteLength)
+            if (len < 0) return null
+            if (len <= buf.byteLength) return buf.slice(0, len)
+            buf = new ArrayBuffer(len)
+        }
+    }
+    /** Write value of key in program storage, visible by other bots after tick */
+    export function set(key: ArrayBuffer, value: ArrayBuffer): bool {
+        return raw.storage_set(changetype<i32>(key), key.byteLength, changetype<i32>(value), value.byteLength) == 0
     }
-}
\ No newline at end of file
+    /** Remove key from program storage after tick */
+    export function remove(key: ArrayBuffer): bool {
+        return raw.storage_delete(changetype<i32>(key), key.byteLength) > 0
+    }
+}
+
+export namespace sys {
+    /** Next deterministic random number, same sequence for a given world and bot */
+    export function random(): u64 {
+        return raw.sys_random() as u64
+    }
+}
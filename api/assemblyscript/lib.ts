@@ -7,9 +7,9 @@ export function _start(): void {
 
 /// Called at each tick (required)
 export function tick(): void {
-  const front = sensors.contact()
-  if (front.isValid()) {
-    motor.rotate_left()
+  if (sensors.contact()) {
+    motor.left()
+  } else {
+    motor.forward()
   }
-  motor.move(2)
 }
\ No newline at end of file
@@ -0,0 +1,55 @@
+// Raw imports of api abi 1
+// Generated by `cargo run -p scalliony-api-gen` from api.json, do not edit
+
+/** Output a log message
+  * Params refer to a string(ptr, len as u32) */
+@external("io@1", "log")
+export declare function io_log(ptr: i32, len: i32): void
+
+/** Output a structured log message
+  * Level is 0 debug, 1 info, 2 warn or 3 error
+  * Fields are key=value lines */
+@external("io@1", "record")
+export declare function io_record(level: i32, ptr: i32, len: i32, fields: i32, fields_len: i32): void
+
+/** Plan to move forward (depending of rotation) of one cell
+  * Action */
+@external("motor@1", "forward")
+export declare function motor_forward(): void
+
+/** Change rotation left relative to current
+  * Action */
+@external("motor@1", "left")
+export declare function motor_left(): void
+
+/** Change rotation right relative to current
+  * Action */
+@external("motor@1", "right")
+export declare function motor_right(): void
+
+/** Check what is blocking cell just in front (depending of rotation)
+  * 0: nothing, 1: wall, 2: bot
+  * Abi 0 returns it as bool */
+@external("sensors@1", "contact")
+export declare function sensors_contact(): i32
+
+/** Remove key after tick
+  * Returns 1 if it was present */
+@external("storage@1", "delete")
+export declare function storage_delete(key: i32, key_len: i32): i32
+
+/** Copy value of key into buffer(ptr, cap)
+  * Returns full value length or -1 if missing
+  * Storage is shared by bots of a program and survives their death */
+@external("storage@1", "get")
+export declare function storage_get(key: i32, key_len: i32, ptr: i32, cap: i32): i32
+
+/** Write value(ptr, len) of key, visible by other bots after tick
+  * Returns -1 if key (max 64), value (max 1024) or storage (max 64KiB) is too large */
+@external("storage@1", "set")
+export declare function storage_set(key: i32, key_len: i32, ptr: i32, len: i32): i32
+
+/** Get next number of a deterministic generator
+  * Seeded by world and bot id, restarts at every boot */
+@external("sys@1", "random")
+export declare function sys_random(): i64
@@ -8,10 +8,17 @@ struct str_t {
 };
 /// Write string to logs
 inline void io_log(const char *str) {
-    _io_log(str, strlen(str));
+    _io_log((int32_t)str, strlen(str));
 }
 inline void io_log_n(const struct str_t *str) {
-    _io_log(str->data, str->size);
+    _io_log((int32_t)str->data, str->size);
+}
+enum level_t: int32_t {
+    Debug = 0, Info, Warn, Error
+};
+/// Write string to logs with a level and fields as "key=value\n" lines
+inline void io_record(enum level_t level, const char *str, const char *fields) {
+    _io_record(level, (int32_t)str, strlen(str), (int32_t)fields, fields ? strlen(fields) : 0);
 }
 
 inline int puts(const char* str) {
@@ -19,49 +26,45 @@ inline int puts(const char* str) {
     return 0;
 }
 inline int puts_n(const char* str, size_t len) {
-    _io_log(str, len);
+    _io_log((int32_t)str, len);
     return 0;
 }
 
 
-inline void motor_rotate(bool left) {
-    _motor_rotate(left ? -1 : 1);
+inline void motor_forward() {
+    _motor_forward();
 }
-inline void motor_rotate_left() {
-    motor_rotate(true);
+inline void motor_left() {
+    _motor_left();
 }
-inline void motor_rotate_right() {
-    motor_rotate(false);
+inline void motor_right() {
+    _motor_right();
 }
 
-/// Move forward of dist cells
-/// Direction depends of current rotation
-/// Actual movement is delayed
-inline void motor_move(uint16_t dist) {
-    _motor_move(dist);
-}
-
-enum entity_type: int32_t {
-    Unexpected = -1,
-    Rock, Bot, Building
-};
-struct entity_t {
-    int64_t id;
-    enum entity_type type;
-};
-enum rotation: int32_t {
-    Up = 0, Right, Down, Left
+enum contact_t: int32_t {
+    None = 0, Wall, Bot
 };
 
-/// Check of returned entity is valid
-inline bool is_valid_entity(struct entity_t e) {
-    return e.id > 0 && e.type != Unexpected;
+/// Check what is blocking path just in front (depending of rotation)
+inline enum contact_t sensors_contact() {
+    return (enum contact_t)_sensors_contact();
+}
+
+/// Copy value of key in program storage into buf
+/// Returns full value length or -1 if missing
+inline int32_t storage_get(const char *key, uint32_t key_len, char *buf, uint32_t cap) {
+    return _storage_get((int32_t)key, key_len, (int32_t)buf, cap);
+}
+/// Write value of key in program storage, visible by other bots after tick
+inline bool storage_set(const char *key, uint32_t key_len, const char *value, uint32_t len) {
+    return _storage_set((int32_t)key, key_len, (int32_t)value, len) == 0;
+}
+/// Remove key from program storage after tick
+inline bool storage_delete(const char *key, uint32_t key_len) {
+    return _storage_delete((int32_t)key, key_len) > 0;
 }
 
-/// Check for entity just in front (depending of rotation)
-/// Returns valid entity if something is in contact
-/// Must check validity with bool is_valid_entity(struct entity_t)
-inline struct entity_t sensors_contact() {
-    struct i64_32_t res = _sensors_contact();
-    return *(struct entity_t*)&res;
+/// Next deterministic random number, same sequence for a given world and bot
+inline uint64_t sys_random() {
+    return (uint64_t)_sys_random();
 }
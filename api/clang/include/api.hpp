@@ -4,53 +4,65 @@
 namespace io {
     /// Write string to logs
     inline void log(const std::string_view &str) {
-        _io_log(str.c_str(), str.size());
+        _io_log(reinterpret_cast<int32_t>(str.c_str()), str.size());
+    }
+    enum class level_t: int32_t {
+        debug = 0, info, warn, error
+    };
+    /// Write string to logs with a level and fields as "key=value\n" lines
+    inline void record(level_t level, const std::string_view &str, const std::string_view &fields) {
+        _io_record(static_cast<int32_t>(level),
+            reinterpret_cast<int32_t>(str.c_str()), str.size(),
+            reinterpret_cast<int32_t>(fields.c_str()), fields.size());
     }
 }
 
 namespace motor {
-    inline void rotate(bool left) {
-        _motor_rotate(left ? -1 : 1);
-    }
-    inline void rotate_left() {
-        rotate(true);
+    inline void forward() {
+        _motor_forward();
     }
-    inline void rotate_right() {
-        rotate(false);
+    inline void left() {
+        _motor_left();
     }
-
-    /// Move forward of dist cells
-    /// Direction depends of current rotation
-    /// Actual movement is delayed
-    inline void move(uint16_t dist) {
-        _motor_move(dist);
+    inline void right() {
+        _motor_right();
     }
 }
 
-
-enum class entity_type: int32_t {
-    Rock, Bot, Building
+enum class contact_t: int32_t {
+    None = 0, Wall, Bot
 };
-struct entity_t {
-    int64_t id;
-    entity_type type;
 
-    /// Check and return entity if valid
-    static inline std::optional<entity_t> Of(i64_32_t raw) {
-        if (raw.a > 0 && raw.b >= 0)
-            return entity_t{raw.a, static_cast<entity_type>(raw.b)};
-        else
-            return {};
+namespace sensors {
+    /// Check what is blocking path just in front (depending of rotation)
+    inline contact_t front() {
+        return static_cast<contact_t>(_sensors_contact());
     }
-};
-enum class rotation: int32_t {
-    Up = 0, Right, Down, Left
-};
+    /// Check if there is something blocking path just in front
+    inline bool contact() {
+        return front() != contact_t::None;
+    }
+}
 
-namespace sensors {
-    /// Check for entity just in front (depending of rotation)
-    /// Returns entity if something is in contact
-    inline std::optional<entity_t> contact() {
-        return entity_t::Of(_sensors_contact());
+namespace storage {
+    /// Copy value of key in program storage into buf
+    /// Returns full value length or -1 if missing
+    inline int32_t get(const std::string_view &key, char *buf, uint32_t cap) {
+        return _storage_get(reinterpret_cast<int32_t>(key.c_str()), key.size(), reinterpret_cast<int32_t>(buf), cap);
+    }
+    /// Write value of key in program storage, visible by other bots after tick
+    inline bool set(const std::string_view &key, const std::string_view &value) {
+        return _storage_set(reinterpret_cast<int32_t>(key.c_str()), key.size(), reinterpret_cast<int32_t>(value.c_str()), value.size()) == 0;
+    }
+    /// Remove key from program storage after tick
+    inline bool remove(const std::string_view &key) {
+        return _storage_delete(reinterpret_cast<int32_t>(key.c_str()), key.size()) > 0;
     }
-}
\ No newline at end of file
+}
+
+namespace sys {
+    /// Next deterministic random number, same sequence for a given world and bot
+    inline uint64_t random() {
+        return static_cast<uint64_t>(_sys_random());
+    }
+}
@@ -1,24 +1,67 @@
+// Raw imports of api abi 1
+// Generated by `cargo run -p scalliony-api-gen` from api.json, do not edit
+#pragma once
 #include <stdint.h>
 
-struct i64_32_t {
-    int64_t a;
-    int32_t b;
-};
-
-void _io_log(const char *s, int32_t l) __attribute__((
-    __import_module__("io"),
+/// Output a log message
+/// Params refer to a string(ptr, len as u32)
+void _io_log(int32_t ptr, int32_t len) __attribute__((
+    __import_module__("io@1"),
     __import_name__("log")));
 
-void _motor_rotate(int32_t left) __attribute__((
-    __import_module__("motor"),
-    __import_name__("rotate")
-));
-void _motor_move(int32_t dist) __attribute__((
-    __import_module__("motor"),
-    __import_name__("move")
-));
-
-struct i64_32_t _sensors_contact() __attribute__((
-    __import_module__("sensors"),
-    __import_name__("contact_s")
-));
+/// Output a structured log message
+/// Level is 0 debug, 1 info, 2 warn or 3 error
+/// Fields are key=value lines
+void _io_record(int32_t level, int32_t ptr, int32_t len, int32_t fields, int32_t fields_len) __attribute__((
+    __import_module__("io@1"),
+    __import_name__("record")));
+
+/// Plan to move forward (depending of rotation) of one cell
+/// Action
+void _motor_forward() __attribute__((
+    __import_module__("motor@1"),
+    __import_name__("forward")));
+
+/// Change rotation left relative to current
+/// Action
+void _motor_left() __attribute__((
+    __import_module__("motor@1"),
+    __import_name__("left")));
+
+/// Change rotation right relative to current
+/// Action
+void _motor_right() __attribute__((
+    __import_module__("motor@1"),
+    __import_name__("right")));
+
+/// Check what is blocking cell just in front (depending of rotation)
+/// 0: nothing, 1: wall, 2: bot
+/// Abi 0 returns it as bool
+int32_t _sensors_contact() __attribute__((
+    __import_module__("sensors@1"),
+    __import_name__("contact")));
+
+/// Remove key after tick
+/// Returns 1 if it was present
+int32_t _storage_delete(int32_t key, int32_t key_len) __attribute__((
+    __import_module__("storage@1"),
+    __import_name__("delete")));
+
+/// Copy value of key into buffer(ptr, cap)
+/// Returns full value length or -1 if missing
+/// Storage is shared by bots of a program and survives their death
+int32_t _storage_get(int32_t key, int32_t key_len, int32_t ptr, int32_t cap) __attribute__((
+    __import_module__("storage@1"),
+    __import_name__("get")));
+
+/// Write value(ptr, len) of key, visible by other bots after tick
+/// Returns -1 if key (max 64), value (max 1024) or storage (max 64KiB) is too large
+int32_t _storage_set(int32_t key, int32_t key_len, int32_t ptr, int32_t len) __attribute__((
+    __import_module__("storage@1"),
+    __import_name__("set")));
+
+/// Get next number of a deterministic generator
+/// Seeded by world and bot id, restarts at every boot
+int64_t _sys_random() __attribute__((
+    __import_module__("sys@1"),
+    __import_name__("random")));
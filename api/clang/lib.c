@@ -7,12 +7,11 @@ void _start() {
 
 /// Called at each tick (required)
 void tick() {
-    struct entity_t front = sensors_contact();
-    if (is_valid_entity(front)) {
-        motor_rotate_left();
+    if (sensors_contact() != None) {
+        motor_left();
     } else {
         //Formatting string at runtime can be expensive
-        //printf("Collide %lld", front.id);
+        //printf("Front %d", sensors_contact());
+        motor_forward();
     }
-    motor_move(3);
 }
\ No newline at end of file
@@ -7,8 +7,9 @@ extern "C" void _start() {
 
 /// Called at each tick (required)
 extern "C" void tick() {
-    if (const auto front = sensors::contact()) {
-        motor::rotate_left();
+    if (sensors::contact()) {
+        motor::left();
+    } else {
+        motor::forward();
     }
-    motor::move(3);
 }
\ No newline at end of file
@@ -0,0 +1,273 @@
+//! Generate bot api bindings from api.json
+//!
+//! Run `cargo run -p scalliony-api-gen` after editing api.json.
+//! With `--check`, fails if bindings are outdated or if the engine does not match api.json.
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+    fs,
+    path::Path,
+};
+
+const GENERATED: &str = "Generated by `cargo run -p scalliony-api-gen` from api.json, do not edit";
+
+#[derive(Deserialize)]
+struct Api {
+    abi: u32,
+    export: BTreeMap<String, BTreeMap<String, Func>>,
+}
+#[derive(Deserialize)]
+struct Func {
+    #[serde(default)]
+    params: Vec<ValType>,
+    /// Optional parameter names
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default, rename = "return")]
+    results: Vec<ValType>,
+    desc: String,
+}
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ValType {
+    I32,
+    I64,
+}
+impl ValType {
+    fn wasm(self) -> &'static str {
+        match self {
+            ValType::I32 => "i32",
+            ValType::I64 => "i64",
+        }
+    }
+    fn c(self) -> &'static str {
+        match self {
+            ValType::I32 => "int32_t",
+            ValType::I64 => "int64_t",
+        }
+    }
+}
+impl Func {
+    fn args(&self) -> impl Iterator<Item = (String, ValType)> + '_ {
+        self.params.iter().enumerate().map(|(i, ty)| {
+            let name = self.names.get(i).cloned();
+            (name.unwrap_or_else(|| format!("arg{}", i)), *ty)
+        })
+    }
+    /// Multi-value is not supported by bindings
+    fn result(&self) -> Result<Option<ValType>> {
+        match self.results[..] {
+            [] => Ok(None),
+            [ty] => Ok(Some(ty)),
+            _ => bail!("multiple return values are not supported"),
+        }
+    }
+    /// Formatted like the engine does
+    fn signature(&self) -> String {
+        let list = |types: &[ValType]| {
+            let types: Vec<_> = types.iter().map(|ty| ty.wasm()).collect();
+            format!("({})", types.join(", "))
+        };
+        format!("{} -> {}", list(&self.params), list(&self.results))
+    }
+}
+impl Api {
+    fn funcs(&self) -> impl Iterator<Item = (&str, &str, &Func)> {
+        self.export.iter().flat_map(|(module, funcs)| {
+            funcs
+                .iter()
+                .map(move |(name, func)| (module.as_str(), name.as_str(), func))
+        })
+    }
+    fn module(&self, module: &str) -> String {
+        match self.abi {
+            0 => module.to_owned(),
+            abi => format!("{}@{}", module, abi),
+        }
+    }
+}
+
+fn rust(api: &Api) -> Result<String> {
+    let mut out = format!(
+        "//! Raw imports of api abi {}\n//!\n//! {}\n",
+        api.abi, GENERATED
+    );
+    for (module, funcs) in api.export.iter() {
+        writeln!(out, "\npub mod {} {{", module)?;
+        writeln!(
+            out,
+            "    #[link(wasm_import_module = \"{}\")]",
+            api.module(module)
+        )?;
+        writeln!(out, "    extern \"C\" {{")?;
+        for (i, (name, func)) in funcs.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            for line in func.desc.lines() {
+                writeln!(out, "        /// {}", line)?;
+            }
+            writeln!(out, "        #[link_name = \"{}\"]", name)?;
+            let args: Vec<_> = func
+                .args()
+                .map(|(arg, ty)| format!("{}: {}", arg, ty.wasm()))
+                .collect();
+            let ret = match func.result()? {
+                Some(ty) => format!(" -> {}", ty.wasm()),
+                None => String::new(),
+            };
+            writeln!(out, "        pub fn {}({}){};", name, args.join(", "), ret)?;
+        }
+        writeln!(out, "    }}\n}}")?;
+    }
+    Ok(out)
+}
+
+fn c(api: &Api) -> Result<String> {
+    let mut out = format!(
+        "// Raw imports of api abi {}\n// {}\n#pragma once\n#include <stdint.h>\n",
+        api.abi, GENERATED
+    );
+    for (module, name, func) in api.funcs() {
+        out.push('\n');
+        for line in func.desc.lines() {
+            writeln!(out, "/// {}", line)?;
+        }
+        let args: Vec<_> = func
+            .args()
+            .map(|(arg, ty)| format!("{} {}", ty.c(), arg))
+            .collect();
+        let ret = func.result()?.map_or("void", ValType::c);
+        writeln!(
+            out,
+            "{} _{}_{}({}) __attribute__((\n    __import_module__(\"{}\"),\n    __import_name__(\"{}\")));",
+            ret,
+            module,
+            name,
+            args.join(", "),
+            api.module(module),
+            name
+        )?;
+    }
+    Ok(out)
+}
+
+fn assemblyscript(api: &Api) -> Result<String> {
+    let mut out = format!("// Raw imports of api abi {}\n// {}\n", api.abi, GENERATED);
+    for (module, name, func) in api.funcs() {
+        out.push('\n');
+        let desc: Vec<_> = func.desc.lines().collect();
+        writeln!(out, "/** {} */", desc.join("\n  * "))?;
+        let args: Vec<_> = func
+            .args()
+            .map(|(arg, ty)| format!("{}: {}", arg, ty.wasm()))
+            .collect();
+        let ret = func.result()?.map_or("void", ValType::wasm);
+        writeln!(
+            out,
+            "@external(\"{}\", \"{}\")\nexport declare function {}_{}({}): {}",
+            api.module(module),
+            name,
+            module,
+            name,
+            args.join(", "),
+            ret
+        )?;
+    }
+    Ok(out)
+}
+
+fn wat(api: &Api) -> Result<String> {
+    let mut out = format!(
+        ";; Raw imports of api abi {}, copy needed ones into your module\n;; {}\n",
+        api.abi, GENERATED
+    );
+    for (module, name, func) in api.funcs() {
+        out.push('\n');
+        for line in func.desc.lines() {
+            writeln!(out, ";; {}", line)?;
+        }
+        write!(
+            out,
+            "(import \"{}\" \"{}\" (func ${}_{}",
+            api.module(module),
+            name,
+            module,
+            name
+        )?;
+        if !func.params.is_empty() {
+            let params: Vec<_> = func.params.iter().map(|ty| ty.wasm()).collect();
+            write!(out, " (param {})", params.join(" "))?;
+        }
+        if let Some(ty) = func.result()? {
+            write!(out, " (result {})", ty.wasm())?;
+        }
+        writeln!(out, "))")?;
+    }
+    Ok(out)
+}
+
+/// Compare api.json with host functions registered by the engine
+fn engine_issues(api: &Api) -> Result<Vec<String>> {
+    let mut issues = Vec::new();
+    if api.abi != engine::ABI {
+        issues.push(format!(
+            "api.json abi is {} but engine abi is {}",
+            api.abi,
+            engine::ABI
+        ));
+    }
+    let expected: BTreeSet<_> = api
+        .funcs()
+        .map(|(module, name, func)| (module.to_owned(), name.to_owned(), func.signature()))
+        .collect();
+    let found: BTreeSet<_> = engine::host_funcs(api.abi)?.into_iter().collect();
+    for (module, name, sig) in expected.difference(&found) {
+        issues.push(format!(
+            "'{}.{}' {} is not registered by the engine",
+            module, name, sig
+        ));
+    }
+    for (module, name, sig) in found.difference(&expected) {
+        issues.push(format!(
+            "'{}.{}' {} is missing in api.json",
+            module, name, sig
+        ));
+    }
+    Ok(issues)
+}
+
+fn main() -> Result<()> {
+    let check = std::env::args().skip(1).any(|arg| arg == "--check");
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let json = fs::read_to_string(root.join("api.json")).context("Failed to read api.json")?;
+    let api: Api = serde_json::from_str(&json).context("Invalid api.json")?;
+
+    let mut issues = engine_issues(&api)?;
+    let outputs = [
+        ("rust/api/src/raw.rs", rust(&api)?),
+        ("clang/include/raw.h", c(&api)?),
+        ("assemblyscript/raw.ts", assemblyscript(&api)?),
+        ("wat/imports.wat", wat(&api)?),
+    ];
+    for (path, content) in outputs {
+        let file = root.join(path);
+        if check {
+            if fs::read_to_string(&file).ok().as_deref() != Some(content.as_str()) {
+                issues.push(format!("api/{} is outdated", path));
+            }
+        } else {
+            fs::write(&file, content).with_context(|| format!("Failed to write api/{}", path))?;
+        }
+    }
+
+    for issue in issues.iter() {
+        eprintln!("{}", issue);
+    }
+    if !issues.is_empty() {
+        bail!("{} issues found", issues.len())
+    }
+    Ok(())
+}
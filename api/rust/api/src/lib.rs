@@ -1,15 +1,40 @@
+/// Generated raw imports
+pub mod raw;
+
 pub mod io {
-    #[link(wasm_import_module = "io")]
-    extern "C" {
-        /// imports io.log
-        #[link_name = "log"]
-        fn _log(s: *const u8, l: u32);
+    /// Severity of a log record
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[repr(i32)]
+    pub enum Level {
+        Debug = 0,
+        Info,
+        Warn,
+        Error,
     }
 
     /// Write string to logs
     pub fn log(s: &str) {
         unsafe {
-            _log(s.as_ptr(), s.len() as u32);
+            crate::raw::io::log(s.as_ptr() as i32, s.len() as i32);
+        }
+    }
+    /// Write string to logs with a level and key-value fields
+    pub fn record(level: Level, s: &str, fields: &[(&str, &str)]) {
+        let mut kv = String::new();
+        for (k, v) in fields {
+            kv.push_str(k);
+            kv.push('=');
+            kv.push_str(v);
+            kv.push('\n');
+        }
+        unsafe {
+            crate::raw::io::record(
+                level as i32,
+                s.as_ptr() as i32,
+                s.len() as i32,
+                kv.as_ptr() as i32,
+                kv.len() as i32,
+            );
         }
     }
 }
@@ -22,42 +47,87 @@ macro_rules! log {
 }
 
 pub mod motor {
-    #[link(wasm_import_module = "motor")]
-    extern "C" {
-        /// imports motor.forward
-        #[link_name = "forward"]
-        fn _forward();
-
-        /// imports motor.left
-        #[link_name = "left"]
-        fn _left();
-
-        /// imports motor.right
-        #[link_name = "right"]
-        fn _right();
-    }
+    use crate::raw::motor as raw;
 
     pub fn forward() {
-        unsafe { _forward() };
+        unsafe { raw::forward() };
     }
     pub fn left() {
-        unsafe { _left() };
+        unsafe { raw::left() };
     }
     pub fn right() {
-        unsafe { _right() };
+        unsafe { raw::right() };
     }
 }
 
 pub mod sensors {
-    #[link(wasm_import_module = "sensors")]
-    extern "C" {
-        /// imports sensors.contact
-        #[link_name = "contact"]
-        fn _contact() -> i32;
+    use crate::raw::sensors as raw;
+
+    /// Kind of cell blocking path
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Contact {
+        Wall,
+        Bot,
     }
 
     /// Check if there is something blocking path just in front (depending of rotation)
     pub fn contact() -> bool {
-        unsafe { _contact() > 0 }
+        front().is_some()
+    }
+    /// Check what is blocking path just in front (depending of rotation)
+    pub fn front() -> Option<Contact> {
+        match unsafe { raw::contact() } {
+            1 => Some(Contact::Wall),
+            2 => Some(Contact::Bot),
+            _ => None,
+        }
+    }
+}
+
+pub mod storage {
+    use crate::raw::storage as raw;
+
+    /// Read value of key in program storage
+    pub fn get(key: &[u8]) -> Option<Vec<u8>> {
+        let mut buf = vec![0; 64];
+        loop {
+            let len = unsafe {
+                raw::get(
+                    key.as_ptr() as i32,
+                    key.len() as i32,
+                    buf.as_mut_ptr() as i32,
+                    buf.len() as i32,
+                )
+            };
+            let len = usize::try_from(len).ok()?;
+            if len <= buf.len() {
+                buf.truncate(len);
+                return Some(buf);
+            }
+            buf.resize(len, 0);
+        }
+    }
+    /// Write value of key in program storage, visible by other bots after tick
+    /// Returns `false` if too large
+    pub fn set(key: &[u8], value: &[u8]) -> bool {
+        unsafe {
+            raw::set(
+                key.as_ptr() as i32,
+                key.len() as i32,
+                value.as_ptr() as i32,
+                value.len() as i32,
+            ) == 0
+        }
+    }
+    /// Remove key from program storage after tick
+    pub fn delete(key: &[u8]) -> bool {
+        unsafe { raw::delete(key.as_ptr() as i32, key.len() as i32) > 0 }
+    }
+}
+
+pub mod sys {
+    /// Next deterministic random number, same sequence for a given world and bot
+    pub fn random() -> u64 {
+        unsafe { crate::raw::sys::random() as u64 }
     }
 }
@@ -0,0 +1,81 @@
+//! Raw imports of api abi 1
+//!
+//! Generated by `cargo run -p scalliony-api-gen` from api.json, do not edit
+
+pub mod io {
+    #[link(wasm_import_module = "io@1")]
+    extern "C" {
+        /// Output a log message
+        /// Params refer to a string(ptr, len as u32)
+        #[link_name = "log"]
+        pub fn log(ptr: i32, len: i32);
+
+        /// Output a structured log message
+        /// Level is 0 debug, 1 info, 2 warn or 3 error
+        /// Fields are key=value lines
+        #[link_name = "record"]
+        pub fn record(level: i32, ptr: i32, len: i32, fields: i32, fields_len: i32);
+    }
+}
+
+pub mod motor {
+    #[link(wasm_import_module = "motor@1")]
+    extern "C" {
+        /// Plan to move forward (depending of rotation) of one cell
+        /// Action
+        #[link_name = "forward"]
+        pub fn forward();
+
+        /// Change rotation left relative to current
+        /// Action
+        #[link_name = "left"]
+        pub fn left();
+
+        /// Change rotation right relative to current
+        /// Action
+        #[link_name = "right"]
+        pub fn right();
+    }
+}
+
+pub mod sensors {
+    #[link(wasm_import_module = "sensors@1")]
+    extern "C" {
+        /// Check what is blocking cell just in front (depending of rotation)
+        /// 0: nothing, 1: wall, 2: bot
+        /// Abi 0 returns it as bool
+        #[link_name = "contact"]
+        pub fn contact() -> i32;
+    }
+}
+
+pub mod storage {
+    #[link(wasm_import_module = "storage@1")]
+    extern "C" {
+        /// Remove key after tick
+        /// Returns 1 if it was present
+        #[link_name = "delete"]
+        pub fn delete(key: i32, key_len: i32) -> i32;
+
+        /// Copy value of key into buffer(ptr, cap)
+        /// Returns full value length or -1 if missing
+        /// Storage is shared by bots of a program and survives their death
+        #[link_name = "get"]
+        pub fn get(key: i32, key_len: i32, ptr: i32, cap: i32) -> i32;
+
+        /// Write value(ptr, len) of key, visible by other bots after tick
+        /// Returns -1 if key (max 64), value (max 1024) or storage (max 64KiB) is too large
+        #[link_name = "set"]
+        pub fn set(key: i32, key_len: i32, ptr: i32, len: i32) -> i32;
+    }
+}
+
+pub mod sys {
+    #[link(wasm_import_module = "sys@1")]
+    extern "C" {
+        /// Get next number of a deterministic generator
+        /// Seeded by world and bot id, restarts at every boot
+        #[link_name = "random"]
+        pub fn random() -> i64;
+    }
+}
@@ -1,9 +1,9 @@
 (module
-    ;; Functions from game api
-    (import "io" "log" (func $io_log (param i32 i32)))
-    (import "sensors" "contact" (func $sensors_contact (result i32)))
-    (import "motor" "forward" (func $motor_forward))
-    (import "motor" "left" (func $motor_left))
+    ;; Functions from game api (see imports.wat)
+    (import "io@1" "log" (func $io_log (param i32 i32)))
+    (import "sensors@1" "contact" (func $sensors_contact (result i32)))
+    (import "motor@1" "forward" (func $motor_forward))
+    (import "motor@1" "left" (func $motor_left))
 
     ;; Memory must be exported
     (memory 1)
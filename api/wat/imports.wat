@@ -0,0 +1,45 @@
+;; Raw imports of api abi 1, copy needed ones into your module
+;; Generated by `cargo run -p scalliony-api-gen` from api.json, do not edit
+
+;; Output a log message
+;; Params refer to a string(ptr, len as u32)
+(import "io@1" "log" (func $io_log (param i32 i32)))
+
+;; Output a structured log message
+;; Level is 0 debug, 1 info, 2 warn or 3 error
+;; Fields are key=value lines
+(import "io@1" "record" (func $io_record (param i32 i32 i32 i32 i32)))
+
+;; Plan to move forward (depending of rotation) of one cell
+;; Action
+(import "motor@1" "forward" (func $motor_forward))
+
+;; Change rotation left relative to current
+;; Action
+(import "motor@1" "left" (func $motor_left))
+
+;; Change rotation right relative to current
+;; Action
+(import "motor@1" "right" (func $motor_right))
+
+;; Check what is blocking cell just in front (depending of rotation)
+;; 0: nothing, 1: wall, 2: bot
+;; Abi 0 returns it as bool
+(import "sensors@1" "contact" (func $sensors_contact (result i32)))
+
+;; Remove key after tick
+;; Returns 1 if it was present
+(import "storage@1" "delete" (func $storage_delete (param i32 i32) (result i32)))
+
+;; Copy value of key into buffer(ptr, cap)
+;; Returns full value length or -1 if missing
+;; Storage is shared by bots of a program and survives their death
+(import "storage@1" "get" (func $storage_get (param i32 i32 i32 i32) (result i32)))
+
+;; Write value(ptr, len) of key, visible by other bots after tick
+;; Returns -1 if key (max 64), value (max 1024) or storage (max 64KiB) is too large
+(import "storage@1" "set" (func $storage_set (param i32 i32 i32 i32) (result i32)))
+
+;; Get next number of a deterministic generator
+;; Seeded by world and bot id, restarts at every boot
+(import "sys@1" "random" (func $sys_random (result i64)))
@@ -1,5 +1,7 @@
 use crate::hex::{Direction, Hex, HexRangeIter};
+use crate::scenario::Scenario;
 pub use bytes::Bytes;
+use std::collections::BTreeMap;
 use std::fmt;
 
 /// Message from the engine
@@ -8,6 +10,10 @@ use std::fmt;
 #[cfg_attr(feature = "serde", serde(tag = "k"))]
 pub enum Event {
     StateChange(State),
+    /// Milliseconds between ticks changed
+    TickRateChange {
+        ms: u64,
+    },
     TickStart {
         tid: TickId,
         ts: Timestamp,
@@ -24,7 +30,8 @@ pub enum Event {
     BotLog {
         #[cfg_attr(feature = "serde", serde(flatten))]
         src: BotSrc,
-        msg: Str,
+        #[cfg_attr(feature = "serde", serde(flatten))]
+        log: LogRecord,
     },
     BotError {
         #[cfg_attr(feature = "serde", serde(flatten))]
@@ -32,11 +39,45 @@ pub enum Event {
         #[cfg_attr(feature = "serde", serde(flatten))]
         err: Error,
     },
+    /// Stopped by [`CrashPolicy::Halt`]
+    BotHalt {
+        #[cfg_attr(feature = "serde", serde(flatten))]
+        src: BotSrc,
+    },
+    /// Stopped by [`CrashPolicy::Reboot`], restarts after `delay` ticks
+    BotReboot {
+        #[cfg_attr(feature = "serde", serde(flatten))]
+        src: BotSrc,
+        delay: u32,
+    },
+    /// Killed by [`CrashPolicy::Kill`], followed by [`Event::BotDie`]
+    BotKill {
+        #[cfg_attr(feature = "serde", serde(flatten))]
+        src: BotSrc,
+    },
+    /// Host call of the debugged bot
+    BotTrace {
+        #[cfg_attr(feature = "serde", serde(flatten))]
+        src: BotSrc,
+        call: HostCall,
+    },
+    /// Debugged bot hit a breakpoint, game is paused
+    BotBreak {
+        #[cfg_attr(feature = "serde", serde(flatten))]
+        src: BotSrc,
+        hit: Breakpoint,
+    },
     BotRotate {
         #[cfg_attr(feature = "serde", serde(flatten))]
         src: BotSrc,
         dir: Direction,
     },
+    /// Received `fuel` from an admin
+    BotRefuel {
+        #[cfg_attr(feature = "serde", serde(flatten))]
+        src: BotSrc,
+        fuel: u64,
+    },
     BotMove {
         #[cfg_attr(feature = "serde", serde(flatten))]
         src: BotSrc,
@@ -48,13 +89,31 @@ pub enum Event {
         to: Hex,
     },
     Cells(CellRange),
-    ProgramAdd {
+    /// Game master failed to spawn a bot, requests get an [`Event::Reply`] instead
+    SpawnError {
         pid: ProgramId,
-        cid: CompileId,
+        to: Hex,
+        err: SpawnErr,
     },
-    CompileError {
-        cid: CompileId,
-        err: Error,
+    /// Answer to the [`Request`] with same `rid`
+    Reply {
+        rid: RequestId,
+        result: Result<Answer, ReplyErr>,
+    },
+    ScenarioProgress {
+        player: PlayerId,
+        /// Index of completed objective
+        oid: u32,
+    },
+    GameOver {
+        winner: Option<PlayerId>,
+    },
+    Announce {
+        msg: Str,
+    },
+    /// Periodic summary of programs statistics
+    MetricsSummary {
+        programs: Vec<ProgramMetrics>,
     },
 }
 impl Event {
@@ -65,26 +124,129 @@ impl Event {
             BotDie { src } => Some(src),
             BotLog { src, .. } => Some(src),
             BotError { src, .. } => Some(src),
+            BotHalt { src } => Some(src),
+            BotReboot { src, .. } => Some(src),
+            BotKill { src } => Some(src),
+            BotTrace { src, .. } => Some(src),
+            BotBreak { src, .. } => Some(src),
             BotRotate { src, .. } => Some(src),
+            BotRefuel { src, .. } => Some(src),
             BotMove { src, .. } => Some(src),
             BotCollide { src, .. } => Some(src),
             StateChange { .. }
+            | TickRateChange { .. }
             | TickStart { .. }
             | TickEnd
             | Cells { .. }
-            | ProgramAdd { .. }
-            | CompileError { .. } => None,
+            | SpawnError { .. }
+            | Reply { .. }
+            | ScenarioProgress { .. }
+            | GameOver { .. }
+            | Announce { .. }
+            | MetricsSummary { .. } => None,
         }
     }
+    /// Bot debugging details only meant for admins
+    pub fn is_debug(&self) -> bool {
+        matches!(self, Event::BotTrace { .. } | Event::BotBreak { .. })
+    }
 }
 
 /// Message to the engine
 #[derive(Debug)]
 pub enum Command {
-    ChangeState(State),
-    Compile(Bytes, Promise<CompileRes>),
-    Spawn(SpawnBody),
+    ChangeState(State, Promise<AdminRes>),
+    /// Set milliseconds between ticks
+    SetTickMs(u64, Promise<AdminRes>),
+    /// Run a single tick while [`State::Paused`], resolved once done
+    Step(Promise<AdminRes>),
+    Compile(Bytes, UserId, Promise<CompileRes>),
+    Spawn(SpawnBody, Promise<SpawnRes>),
     Map(HexRange, Promise<CellRange>),
+    /// Reset world with given scenario
+    LoadScenario(Box<Scenario>, Promise<LoadRes>),
+    /// Replace game master program
+    LoadMaster(Bytes, Promise<LoadRes>),
+    SetCrashPolicy(CrashPolicy),
+    /// Trace a single bot or stop debugging with `None`
+    Debug(Option<DebugBody>, Promise<AdminRes>),
+    /// Statistics of all programs and alive bots
+    Metrics(Promise<Metrics>),
+    Bot(BotId, Promise<Option<BotInfo>>),
+    Bots(Promise<Vec<BotInfo>>),
+    Cell(Hex, Promise<Cell>),
+    Program(ProgramId, Promise<Option<ProgramInfo>>),
+    TickId(Promise<TickId>),
+    /// Remove bot without crash policy
+    Despawn(BotId, Promise<AdminRes>),
+    /// Move bot to an empty cell
+    Teleport(BotId, Hex, Promise<AdminRes>),
+    SetFacing(BotId, Direction, Promise<AdminRes>),
+    AddFuel(BotId, u64, Promise<AdminRes>),
+}
+
+/// Snapshot of a bot
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BotInfo {
+    pub bid: BotId,
+    pub pid: ProgramId,
+    /// Scenario owner
+    pub player: Option<PlayerId>,
+    pub at: Hex,
+    pub facing: Direction,
+    /// Booted, otherwise waiting for fuel or stopped by crash policy
+    pub running: bool,
+}
+
+/// Snapshot of a program
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProgramInfo {
+    pub pid: ProgramId,
+    /// Alive bots
+    pub bots: u32,
+    /// Code length in bytes
+    pub size: u32,
+    /// Storage used by keys and values of all owners
+    pub storage: u32,
+}
+
+/// What to do with a bot after consecutive errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "k"))]
+pub enum CrashPolicy {
+    /// Stop it for good
+    Halt { errors: u32 },
+    /// Stop it, then boot it again after `backoff` ticks,
+    /// doubled at each reboot up to `max_backoff`
+    Reboot {
+        errors: u32,
+        backoff: u32,
+        max_backoff: u32,
+    },
+    /// Remove it from the world
+    Kill { errors: u32 },
+}
+impl CrashPolicy {
+    /// Number of consecutive errors triggering policy
+    pub fn errors(&self) -> u32 {
+        match *self {
+            Self::Halt { errors } | Self::Reboot { errors, .. } | Self::Kill { errors } => {
+                errors.max(1)
+            }
+        }
+    }
+}
+impl Default for CrashPolicy {
+    fn default() -> Self {
+        Self::Reboot {
+            errors: 3,
+            backoff: 8,
+            max_backoff: 512,
+        }
+    }
 }
 
 /// Over the network [`Command`]
@@ -96,11 +258,256 @@ pub enum Rpc {
     Map(HexRange),
     Spawn(SpawnBody),
     ChangeState(State),
-    Compile { cid: CompileId, code: Bytes },
+    SetTickMs {
+        ms: u64,
+    },
+    Step,
+    Compile {
+        code: Bytes,
+    },
+    /// Stop debugging without body
+    Debug {
+        #[cfg_attr(feature = "serde", serde(default))]
+        body: Option<DebugBody>,
+    },
+    Despawn {
+        bid: BotId,
+    },
+    Teleport {
+        bid: BotId,
+        to: Hex,
+    },
+    SetFacing {
+        bid: BotId,
+        dir: Direction,
+    },
+    AddFuel {
+        bid: BotId,
+        fuel: u64,
+    },
+}
+impl Rpc {
+    /// Restricted to server admins
+    pub fn is_admin(&self) -> bool {
+        use Rpc::*;
+        match self {
+            ChangeState(_)
+            | SetTickMs { .. }
+            | Step
+            | Debug { .. }
+            | Despawn { .. }
+            | Teleport { .. }
+            | SetFacing { .. }
+            | AddFuel { .. } => true,
+            SetView(_) | Map(_) | Spawn(_) | Compile { .. } => false,
+        }
+    }
+}
+
+/// [`Rpc`] with an identifier echoed by [`Event::Reply`]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Request {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rid: RequestId,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub rpc: Rpc,
+}
+impl Request {
+    /// Engine command calling `reply` with its [`Event::Reply`],
+    /// `None` for [`Rpc::SetView`] which is handled by the caller
+    pub fn into_command(
+        self,
+        user: UserId,
+        reply: impl FnOnce(Event) + Send + 'static,
+    ) -> Option<Command> {
+        let rid = self.rid;
+        let reply = move |result| reply(Event::Reply { rid, result });
+        fn done(
+            reply: impl FnOnce(Result<Answer, ReplyErr>) + Send + 'static,
+        ) -> Promise<AdminRes> {
+            Promise::new(move |r: AdminRes| reply(r.map(|()| Answer::Done)))
+        }
+        Some(match self.rpc {
+            Rpc::SetView(_) => return None,
+            Rpc::Map(q) => Command::Map(q, Promise::new(move |cr| reply(Ok(Answer::Cells(cr))))),
+            Rpc::Spawn(q) => Command::Spawn(
+                q,
+                Promise::new(move |r: SpawnRes| {
+                    reply(
+                        r.map(|bid| Answer::Bot { bid })
+                            .map_err(|err| ReplyErr::Spawn { err }),
+                    )
+                }),
+            ),
+            Rpc::Compile { code } => Command::Compile(
+                code,
+                user,
+                Promise::new(move |r: CompileRes| {
+                    reply(
+                        r.map(|pid| Answer::Program { pid })
+                            .map_err(ReplyErr::Compile),
+                    )
+                }),
+            ),
+            Rpc::Despawn { bid } => Command::Despawn(bid, done(reply)),
+            Rpc::Teleport { bid, to } => Command::Teleport(bid, to, done(reply)),
+            Rpc::SetFacing { bid, dir } => Command::SetFacing(bid, dir, done(reply)),
+            Rpc::AddFuel { bid, fuel } => Command::AddFuel(bid, fuel, done(reply)),
+            Rpc::ChangeState(v) => Command::ChangeState(v, done(reply)),
+            Rpc::SetTickMs { ms } => Command::SetTickMs(ms, done(reply)),
+            Rpc::Step => Command::Step(done(reply)),
+            Rpc::Debug { body } => Command::Debug(body, done(reply)),
+        })
+    }
+}
+
+/// Successful result of a [`Request`]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "k"))]
+pub enum Answer {
+    /// Accepted by the engine
+    Done,
+    Cells(CellRange),
+    Program {
+        pid: ProgramId,
+    },
+    Bot {
+        bid: BotId,
+    },
+}
+
+/// Failure of a [`Request`]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "k"))]
+pub enum ReplyErr {
+    /// Restricted to admins
+    Denied,
+    /// Malformed request
+    Bad {
+        err: Str,
+    },
+    Compile(CompileErr),
+    Spawn {
+        err: SpawnErr,
+    },
+    /// No alive bot with this id
+    UnknownBot,
+    /// Target cell is not empty
+    BusyCell,
+    /// Step requested while game is not paused
+    NotPaused,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DebugBody {
+    pub bid: BotId,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub breakpoints: Vec<Breakpoint>,
+}
+
+/// Condition pausing the game on the debugged bot
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "k"))]
+pub enum Breakpoint {
+    /// Planned move is blocked
+    Collide,
+    /// Trap or any other error
+    Error,
+    /// Host function like `motor.left` is called
+    Call { func: Str },
+}
+
+/// Execution statistics of a bot or a program
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Stats {
+    /// Ticks run
+    pub ticks: u64,
+    /// Wasm fuel consumed by ticks
+    pub fuel: u64,
+    /// Most fuel consumed by a single tick
+    pub max_fuel: u64,
+    /// Host calls by unversioned name like `motor.left`
+    pub calls: BTreeMap<Str, u64>,
+    pub errors: u64,
+    /// Planned moves
+    pub moves: u64,
+    /// Blocked moves
+    pub collisions: u64,
+    /// Travelled cells
+    pub distance: u64,
+}
+impl Stats {
+    pub fn merge(&mut self, other: &Stats) {
+        self.ticks += other.ticks;
+        self.fuel += other.fuel;
+        self.max_fuel = self.max_fuel.max(other.max_fuel);
+        for (name, n) in other.calls.iter() {
+            *self.calls.entry(name.clone()).or_default() += n;
+        }
+        self.errors += other.errors;
+        self.moves += other.moves;
+        self.collisions += other.collisions;
+        self.distance += other.distance;
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProgramMetrics {
+    pub pid: ProgramId,
+    /// Alive bots
+    pub bots: u32,
+    /// Including dead bots
+    pub stats: Stats,
+}
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BotMetrics {
+    pub bid: BotId,
+    pub pid: ProgramId,
+    pub stats: Stats,
+}
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metrics {
+    pub programs: Vec<ProgramMetrics>,
+    pub bots: Vec<BotMetrics>,
+}
+
+/// Traced host function call
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HostCall {
+    /// Unversioned name like `motor.left`
+    pub func: Str,
+    pub args: Vec<i64>,
+    /// Empty if trapped
+    pub ret: Vec<i64>,
+    /// Consumed by host function
+    pub fuel: u64,
+    pub trap: bool,
+}
+impl fmt::Display for HostCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{:?}", self.func, self.args)?;
+        if self.trap {
+            f.write_str(" trapped")?;
+        } else if !self.ret.is_empty() {
+            write!(f, " -> {:?}", self.ret)?;
+        }
+        write!(f, " ({} fuel)", self.fuel)
+    }
 }
 
 /// A cheaply clonable readonly String
-#[derive(PartialEq, Eq, PartialOrd, Ord, Default, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Default, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize))]
 #[cfg_attr(feature = "serde", serde(from = "String"))]
 pub struct Str(Bytes);
@@ -204,7 +611,7 @@ impl fmt::Display for BotId {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 #[repr(transparent)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "serde", serde(transparent))]
@@ -230,8 +637,32 @@ impl From<ProgramId> for usize {
     }
 }
 
-/// Compilation request identifier
-pub type CompileId = u32;
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, PartialOrd, Ord)]
+#[repr(transparent)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct PlayerId(u32);
+impl From<u32> for PlayerId {
+    fn from(v: u32) -> Self {
+        Self(v)
+    }
+}
+impl From<PlayerId> for u32 {
+    fn from(p: PlayerId) -> Self {
+        p.0
+    }
+}
+impl fmt::Display for PlayerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Player{}", self.0)
+    }
+}
+
+/// Opaque user identifier
+pub type UserId = Str;
+
+/// Client chosen [`Request`] identifier
+pub type RequestId = u32;
 
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum Cell {
@@ -251,16 +682,81 @@ impl Cell {
 pub struct Error {
     pub ctx: Str,
     pub err: Str,
+    /// Bot backtrace, innermost first
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub frames: Vec<Frame>,
 }
 impl Error {
     pub fn new(ctx: &'static str, err: String) -> Self {
         Self {
             ctx: ctx.into(),
             err: err.into(),
+            frames: Vec::new(),
         }
     }
 }
 
+/// Symbolicated wasm call frame
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Frame {
+    /// Function index in module
+    pub func: u32,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub name: Option<Str>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub file: Option<Str>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub line: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub col: Option<u32>,
+}
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{}", name)?,
+            None => write!(f, "<wasm function {}>", self.func)?,
+        }
+        if let Some(file) = &self.file {
+            write!(f, " ({}", file)?;
+            if let Some(line) = self.line {
+                write!(f, ":{}", line)?;
+                if let Some(col) = self.col {
+                    write!(f, ":{}", col)?;
+                }
+            }
+            f.write_str(")")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// Structured message written by a bot
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogRecord {
+    pub level: LogLevel,
+    pub msg: Str,
+    /// Key-value pairs
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub fields: Vec<(Str, Str)>,
+}
+
 #[derive(Copy, Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BotSrc {
@@ -277,7 +773,63 @@ pub enum State {
     Stopped,
 }
 
-pub type CompileRes = Result<ProgramId, Error>;
+/// Compilation failure with link diagnostics
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompileErr {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub err: Error,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub issues: Vec<LinkIssue>,
+}
+impl From<Error> for CompileErr {
+    fn from(err: Error) -> Self {
+        Self {
+            err,
+            issues: Vec::new(),
+        }
+    }
+}
+/// Reason for a program to be rejected, signatures are formatted like `(i32) -> ()`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "k"))]
+pub enum LinkIssue {
+    /// Import not provided by the api
+    UnknownImport {
+        module: Str,
+        name: Str,
+    },
+    ImportSignature {
+        module: Str,
+        name: Str,
+        expected: Str,
+        /// `None` if not a function
+        found: Option<Str>,
+    },
+    /// Imports from different api versions
+    MixedVersions {
+        versions: Vec<u32>,
+    },
+    MissingExport {
+        name: Str,
+    },
+    ExportSignature {
+        name: Str,
+        expected: Str,
+        /// `None` if not a function
+        found: Option<Str>,
+    },
+}
+
+pub type CompileRes = Result<ProgramId, CompileErr>;
+pub type LoadRes = Result<(), Error>;
+pub type SpawnRes = Result<BotId, SpawnErr>;
+/// Result of an admin [`Command`] on a bot
+pub type AdminRes = Result<(), ReplyErr>;
 
 /// Number of non-leap-milliseconds since January 1, 1970 UTC
 #[derive(Clone, Copy)]
@@ -409,6 +961,32 @@ impl Area {
 pub struct SpawnBody {
     pub pid: ProgramId,
     pub to: Hex,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub facing: Direction,
+    /// Initial fuel budget, rule default if missing
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub fuel: Option<u64>,
+}
+
+/// Reason for a bot not to be spawned
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "k"))]
+pub enum SpawnErr {
+    UnknownProgram,
+    /// Target cell is not empty
+    BusyCell,
+    /// Initial fuel is over `max`
+    TooMuchFuel {
+        max: u64,
+    },
+    /// Initial fuel is under `min`, bot could never boot
+    NotEnoughFuel {
+        min: u64,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -148,13 +148,14 @@ impl ExactSizeIterator for HexRangeIter {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
 #[repr(u8)]
 #[cfg_attr(
     feature = "serde",
     derive(serde_repr::Serialize_repr, serde_repr::Deserialize_repr)
 )]
 pub enum Direction {
+    #[default]
     Up = 0,
     UpRight,
     DownRight,
@@ -1,3 +1,4 @@
 pub mod dto;
 pub mod hex;
+pub mod scenario;
 pub mod zorder;
@@ -0,0 +1,71 @@
+//! Scenario definition with objectives and victory conditions
+
+use crate::dto::{Bytes, PlayerId, Str};
+use crate::hex::{Direction, Hex};
+
+/// Scenario file content
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Scenario {
+    pub name: Str,
+    pub map: ScenarioMap,
+    /// Programs code (wasm or wat) used by initial bots
+    pub programs: Vec<Bytes>,
+    /// Initial bots indexed by [`PlayerId`]
+    pub players: Vec<Vec<ScenarioBot>>,
+    /// Game over after this number of ticks
+    pub time_limit: Option<u32>,
+    /// A player wins when all of them are completed
+    pub objectives: Vec<Objective>,
+}
+impl Scenario {
+    pub fn player_ids(&self) -> impl Iterator<Item = PlayerId> {
+        (0..self.players.len() as u32).map(PlayerId::from)
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ScenarioMap {
+    pub seed: u32,
+    /// Forced wall cells
+    pub walls: Vec<Hex>,
+    /// Forced ground cells
+    pub grounds: Vec<Hex>,
+}
+impl Default for ScenarioMap {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            walls: Vec::new(),
+            grounds: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScenarioBot {
+    /// Index in [`Scenario::programs`]
+    pub program: u32,
+    pub at: Hex,
+    #[cfg_attr(feature = "serde", serde(default = "default_facing"))]
+    pub facing: Direction,
+}
+#[cfg(feature = "serde")]
+fn default_facing() -> Direction {
+    Direction::Up
+}
+
+/// Predicate evaluated each tick for each player
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "k"))]
+pub enum Objective {
+    /// Any bot of the player stands on given cell
+    ReachHex { at: Hex },
+    /// All bots of other players are dead, never met with less than two players
+    LastPlayerStanding,
+}
@@ -3,6 +3,7 @@ mod offline;
 #[cfg(feature = "online")]
 mod online;
 pub use bulb::dto::*;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 #[cfg(not(any(feature = "online", feature = "offline")))]
 compile_error!("Either feature 'online' or 'offline' must be enabled");
@@ -10,7 +11,7 @@ compile_error!("Either feature 'online' or 'offline' must be enabled");
 trait Client {
     fn update(&mut self) {}
     fn try_recv(&mut self) -> Option<Event>;
-    fn send(&mut self, c: Rpc);
+    fn send(&mut self, req: Request);
     fn connected(&self) -> bool {
         true
     }
@@ -56,13 +57,18 @@ impl Any {
             Self::On(c) => c.try_recv(),
         }
     }
-    pub fn send(&mut self, v: Rpc) {
+    /// Send with a new request id, echoed by [`Event::Reply`]
+    pub fn send(&mut self, rpc: Rpc) -> RequestId {
+        static NEXT_RID: AtomicU32 = AtomicU32::new(1);
+        let rid = NEXT_RID.fetch_add(1, Ordering::Relaxed);
+        let req = Request { rid, rpc };
         match self {
             #[cfg(feature = "offline")]
-            Self::Off(c) => c.send(v),
+            Self::Off(c) => c.send(req),
             #[cfg(feature = "online")]
-            Self::On(c) => c.send(v),
+            Self::On(c) => c.send(req),
         }
+        rid
     }
     pub fn connected(&self) -> bool {
         match self {
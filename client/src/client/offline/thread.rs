@@ -1,7 +1,5 @@
 use engine::*;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::sync::Mutex;
 use std::{sync::mpsc, thread};
 
 pub struct Client {
@@ -9,36 +7,26 @@ pub struct Client {
     events_rx: mpsc::Receiver<Event>,
     events_tx: mpsc::Sender<Event>,
     thread: Option<thread::JoinHandle<()>>,
-    //TODO: move speed managment to bulb
-    tick_ms: Arc<AtomicU64>,
 }
 impl Client {
     pub fn new() -> Self {
         let (commands_tx, commands_rx) = mpsc::channel();
         let (events_tx, events_rx) = mpsc::channel();
 
-        let tick_ms = Arc::new(AtomicU64::new(DEFAULT_TICK_DURATION_MS));
-
         let evs = events_tx.clone();
+        // Commands are received while waiting for next tick
         let mut game = GameState::new(
-            move || commands_rx.try_recv().ok(),
+            || None,
             move |v: Event| {
                 _ = evs.send(v);
             },
             false,
+            DEFAULT_TICK_DURATION_MS,
         );
 
-        let thread_tick_ms = tick_ms.clone();
         let thread = thread::Builder::new()
             .name("game-master".into())
-            .spawn(move || {
-                while game.update() != State::Stopped {
-                    //TODO: time step
-                    thread::sleep(Duration::from_millis(
-                        thread_tick_ms.load(Ordering::Relaxed),
-                    ))
-                }
-            })
+            .spawn(move || game.run(|timeout| commands_rx.recv_timeout(timeout)))
             .unwrap();
 
         Self {
@@ -46,13 +34,15 @@ impl Client {
             events_rx,
             events_tx,
             thread: Some(thread),
-            tick_ms,
         }
     }
 }
 impl Drop for Client {
     fn drop(&mut self) {
         if let Some(thread) = self.thread.take() {
+            _ = self
+                .commands
+                .send(Command::ChangeState(State::Stopped, Promise::new(|_| {})));
             _ = thread.join();
         }
     }
@@ -63,34 +53,11 @@ impl super::super::Client for Client {
         self.events_rx.try_recv().ok()
     }
     #[inline]
-    fn send(&mut self, c: Rpc) {
-        //TODO: if tick_rate update tick_ms
-        if let Some(cmd) = match c {
-            Rpc::ChangeState(v) => Some(Command::ChangeState(v)),
-            Rpc::Spawn(q) => Some(Command::Spawn(q)),
-            Rpc::Map(q) => {
-                let evs = self.events_tx.clone();
-                Some(Command::Map(
-                    q,
-                    Promise::new(move |cr| {
-                        _ = evs.send(Event::Cells(cr));
-                    }),
-                ))
-            }
-            Rpc::SetView { .. } => None,
-            Rpc::Compile { cid, code } => {
-                let evs = self.events_tx.clone();
-                Some(Command::Compile(
-                    code,
-                    Promise::new(move |r| {
-                        _ = evs.send(match r {
-                            Ok(pid) => Event::ProgramAdd { cid, pid },
-                            Err(err) => Event::CompileError { cid, err },
-                        })
-                    }),
-                ))
-            }
-        } {
+    fn send(&mut self, req: Request) {
+        let evs = self.events_tx.clone();
+        if let Some(cmd) = req.into_command("local".into(), move |e| {
+            _ = evs.send(e);
+        }) {
             _ = self.commands.send(cmd);
         }
     }
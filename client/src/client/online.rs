@@ -1,5 +1,5 @@
 use bulb::dto::*;
-use quad_net::http_request::{Method, Request, RequestBuilder};
+use quad_net::http_request::{Method, RequestBuilder};
 use quad_net::web_socket::WebSocket;
 
 pub struct Client {
@@ -25,8 +25,8 @@ impl super::Client for Client {
         }
         None
     }
-    fn send(&mut self, rpc: Rpc) {
-        let packet = serde_json::to_string(&rpc).unwrap();
+    fn send(&mut self, req: Request) {
+        let packet = serde_json::to_string(&req).unwrap();
         self.ws.send_text(&packet)
     }
     fn connected(&self) -> bool {
@@ -98,8 +98,12 @@ pub struct AnimatedState {
     tick: Option<(TickId, Timestamp)>,
     next_tick: Option<(TickId, Timestamp)>,
     state: Option<bulb::dto::State>,
+    /// Last host calls of debugged bot
+    trace: std::collections::VecDeque<String>,
 }
 impl AnimatedState {
+    const TRACE_LEN: usize = 64;
+
     pub fn apply_one(&mut self, e: Event) {
         trace!("{:?}", e);
         use Event::*;
@@ -121,9 +125,11 @@ impl AnimatedState {
                 self.next_tick = Some((tid, ts));
             }
             StateChange(state) => self.state = Some(state),
+            TickRateChange { ms } => info!("Tick every {}ms", ms),
             Cells(cr) => self.next.map.extend(cr.iter()),
             BotSpawn { src } => _ = self.next.bot_mut(&src),
             BotRotate { src, dir } => self.next.bot_mut(&src).dir = Some(dir),
+            BotRefuel { src, fuel } => info!("{:?} refueled {}", src, fuel),
             BotMove { src, to } => {
                 let from = std::mem::replace(&mut self.next.bot_mut(&src).at, to);
                 let _prev = self.next.map.insert(from, Cell::Ground);
@@ -137,13 +143,53 @@ impl AnimatedState {
                     self.next.map.insert(bot.at, Cell::Ground);
                 }
             }
-            BotLog { src, msg } => info!("{:?} log {}", src, msg),
-            BotError { src, err } => warn!("{:?} err {:?}", src, err),
-            ProgramAdd { cid, pid } => {
-                self.next.programs.push(pid);
-                info!("CompileId({}) ok {:?}", cid, pid)
+            BotLog { src, log } => match log.level {
+                LogLevel::Debug => debug!("{:?} log {} {:?}", src, log.msg, log.fields),
+                LogLevel::Info => info!("{:?} log {} {:?}", src, log.msg, log.fields),
+                LogLevel::Warn => warn!("{:?} log {} {:?}", src, log.msg, log.fields),
+                LogLevel::Error => error!("{:?} log {} {:?}", src, log.msg, log.fields),
+            },
+            BotError { src, err } => {
+                warn!("{:?} err {}: {}", src, err.ctx, err.err);
+                for frame in err.frames.iter() {
+                    warn!("    at {}", frame);
+                }
+            }
+            BotHalt { src } => warn!("{:?} halted", src),
+            BotReboot { src, delay } => warn!("{:?} rebooting in {} ticks", src, delay),
+            BotKill { src } => warn!("{:?} killed", src),
+            BotTrace { call, .. } => {
+                if self.trace.len() >= Self::TRACE_LEN {
+                    self.trace.pop_front();
+                }
+                self.trace.push_back(call.to_string());
+            }
+            BotBreak { src, hit } => {
+                warn!("{:?} hit {:?}", src, hit);
+                self.trace.push_back(format!("break on {:?}", hit));
+            }
+            SpawnError { pid, to, err } => error!("Spawn {:?} at {:?} err {:?}", pid, to, err),
+            Reply { rid, result } => match result {
+                Ok(Answer::Done) => trace!("RequestId({}) done", rid),
+                Ok(Answer::Cells(cr)) => self.next.map.extend(cr.iter()),
+                Ok(Answer::Program { pid }) => {
+                    self.next.programs.push(pid);
+                    info!("RequestId({}) compiled {:?}", rid, pid)
+                }
+                Ok(Answer::Bot { bid }) => info!("RequestId({}) spawned {:?}", rid, bid),
+                Err(err) => error!("RequestId({}) err {:?}", rid, err),
+            },
+            ScenarioProgress { player, oid } => info!("{} completed objective {}", player, oid),
+            GameOver { winner } => warn!("Game over, winner {:?}", winner),
+            Announce { msg } => warn!("Announce: {}", msg),
+            MetricsSummary { programs } => {
+                for p in programs {
+                    debug!(
+                        "{:?}: {} bots, {} ticks, {} fuel, {} errors",
+                        p.pid, p.bots, p.stats.ticks, p.stats.fuel, p.stats.errors
+                    );
+                }
             }
-            CompileError { cid, err } => error!("CompileId({}) err {:?}", cid, err),
         }
     }
     pub fn apply(&mut self, it: impl Iterator<Item = Event>) -> bool {
@@ -169,11 +215,33 @@ impl AnimatedState {
     pub fn programs(&self) -> &[ProgramId] {
         self.cur.programs()
     }
+    #[inline]
+    pub fn trace(&self) -> impl Iterator<Item = &str> {
+        self.trace.iter().map(String::as_str)
+    }
+    #[inline]
+    pub fn state(&self) -> Option<bulb::dto::State> {
+        self.state
+    }
 }
 
 pub fn compile(client: &mut Client, code: Bytes) {
-    client.send(Rpc::Compile { cid: 0, code });
+    client.send(Rpc::Compile { code });
 }
 pub fn spawn(client: &mut Client, pid: ProgramId, to: Hex) {
-    client.send(Rpc::Spawn(SpawnBody { pid, to }))
+    client.send(Rpc::Spawn(SpawnBody {
+        pid,
+        to,
+        facing: Direction::Up,
+        fuel: None,
+    }));
+}
+pub fn debug(client: &mut Client, body: Option<DebugBody>) {
+    client.send(Rpc::Debug { body });
+}
+pub fn change_state(client: &mut Client, state: bulb::dto::State) {
+    client.send(Rpc::ChangeState(state));
+}
+pub fn step(client: &mut Client) {
+    client.send(Rpc::Step);
 }
@@ -35,6 +35,8 @@ async fn main() {
     let mut code = Code::default();
     let mut program: usize = 0;
     let mut spawn: Hex = Hex::default();
+    let mut debug_bot: u64 = 0;
+    let mut debug_breaks = (false, false, String::new());
 
     let mut state = game::AnimatedState::default();
 
@@ -93,6 +95,60 @@ async fn main() {
                 });
             }
 
+            Window::new("Debug").default_open(false).show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Bot:");
+                    ui.add(DragValue::new(&mut debug_bot));
+                });
+                ui.checkbox(&mut debug_breaks.0, "Break on collide");
+                ui.checkbox(&mut debug_breaks.1, "Break on error");
+                ui.horizontal(|ui| {
+                    ui.label("Break on call:");
+                    ui.text_edit_singleline(&mut debug_breaks.2);
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Trace").clicked() {
+                        use bulb::dto::Breakpoint;
+                        let mut breakpoints = Vec::new();
+                        if debug_breaks.0 {
+                            breakpoints.push(Breakpoint::Collide);
+                        }
+                        if debug_breaks.1 {
+                            breakpoints.push(Breakpoint::Error);
+                        }
+                        if !debug_breaks.2.is_empty() {
+                            breakpoints.push(Breakpoint::Call {
+                                func: debug_breaks.2.clone().into(),
+                            });
+                        }
+                        game::debug(
+                            &mut client,
+                            Some(bulb::dto::DebugBody {
+                                bid: debug_bot.into(),
+                                breakpoints,
+                            }),
+                        )
+                    }
+                    if ui.button("Stop").clicked() {
+                        game::debug(&mut client, None)
+                    }
+                    if state.state() == Some(bulb::dto::State::Paused) {
+                        if ui.button("Resume").clicked() {
+                            game::change_state(&mut client, bulb::dto::State::Running)
+                        }
+                        if ui.button("Step").clicked() {
+                            game::step(&mut client)
+                        }
+                    }
+                });
+                ui.separator();
+                ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+                    for line in state.trace() {
+                        ui.monospace(line);
+                    }
+                });
+            });
+
             Window::new("Tracker")
                 .title_bar(false)
                 .anchor(Align2::RIGHT_TOP, (-5., 5.))
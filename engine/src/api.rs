@@ -1,6 +1,14 @@
 use super::bot::{self, Action};
-use super::Error;
-use sys::wasm::{self, spec::StoreRef, CallerMemoryExt};
+use super::storage::MAX_KEY_LEN;
+use super::wasm::{
+    self,
+    spec::{HostFunc, Linker as _, Store as _, Value},
+};
+use super::{
+    Cell, CompileErr, Error, Frame, HostCall, LinkIssue, LogLevel, LogRecord, Str,
+    DEFAULT_TICK_DURATION_MS,
+};
+use sys::log::{Level, Record};
 use sys::Result;
 
 pub const MIN_BOOT_FUEL: u64 = 64;
@@ -8,6 +16,28 @@ const LOG_FUEL_BASE: u64 = 16;
 const LOG_FUEL_RATIO: u64 = 2;
 pub const TURN_FUEL: u64 = 32;
 pub const MOVE_FUEL: u64 = 256;
+pub const RANDOM_FUEL: u64 = 8;
+/// Initial fuel of spawned bots
+pub const SPAWN_FUEL: u64 = 10_000;
+pub const MAX_SPAWN_FUEL: u64 = 100_000;
+const STORAGE_FUEL_BASE: u64 = 32;
+const STORAGE_FUEL_RATIO: u64 = 1;
+/// Virtual wasi clocks step of a tick
+pub const WASI_TICK_NS: u64 = DEFAULT_TICK_DURATION_MS * 1_000_000;
+
+/// Latest api version, imported from modules like `motor@1`
+///
+/// Unversioned module names are version `0`.
+pub const ABI: u32 = 1;
+/// `sensors.contact` results since version `1`
+const CONTACT_NONE: i32 = 0;
+const CONTACT_WALL: i32 = 1;
+const CONTACT_BOT: i32 = 2;
+
+/// Optional export called once booted
+pub const ON_BOOT: &str = "on_boot";
+/// Optional export called when a planned move is blocked
+pub const ON_COLLIDE: &str = "on_collide";
 
 pub type VM = wasm::Linker<bot::Store>;
 #[inline]
@@ -20,39 +50,214 @@ pub fn new_vm() -> Result<VM> {
             required: true,
             value: wasm::spec::ExportType::UnitFunc,
         });
+    for name in [ON_BOOT, ON_COLLIDE] {
+        vm.add_export(wasm::spec::LinkExport {
+            name,
+            required: false,
+            value: wasm::spec::ExportType::UnitFunc,
+        });
+    }
+
+    for abi in 0..=ABI {
+        add_api(&mut vm, abi)?;
+    }
+    Ok(vm)
+}
 
-    vm.add_func("io", "log", |mut bot: Caller, ptr: u32, len: u32| {
+/// Register host functions of an api version under `module@abi`
+///
+/// Every version stays linkable so programs built against older bindings keep working.
+fn add_api(vm: &mut VM, abi: u32) -> Result<()> {
+    let module = |name| wasm::spec::versioned(name, abi);
+    let vm = &mut Instrumented(vm);
+
+    vm.add_func(&module("io"), "log", |bot: Caller, ptr: u32, len: u32| {
         bot.consume_fuel(len as u64 * LOG_FUEL_RATIO + LOG_FUEL_BASE)?;
-        let (buf, ctx) = with_mem(&mut bot, ptr, len)?;
-        ctx.write_log(buf);
+        let (buf, ctx) = with_mem(bot, ptr, len)?;
+        let msg = String::from_utf8_lossy(buf).into_owned();
+        ctx.push_log(Record::new(Level::Info, msg));
         Ok(())
-    })?;
+    })?
+    .add_func(
+        &module("io"),
+        "record",
+        |bot: Caller, level: i32, ptr: u32, len: u32, fields: u32, fields_len: u32| {
+            bot.consume_fuel((len as u64 + fields_len as u64) * LOG_FUEL_RATIO + LOG_FUEL_BASE)?;
+            let level = Level::from_i32(level).unwrap_or(Level::Info);
+            let fields = String::from_utf8_lossy(with_mem(bot, fields, fields_len)?.0)
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| match line.split_once('=') {
+                    Some((k, v)) => (k.to_owned(), v.to_owned()),
+                    None => (line.to_owned(), String::new()),
+                })
+                .collect();
+            let (buf, ctx) = with_mem(bot, ptr, len)?;
+            let msg = String::from_utf8_lossy(buf).into_owned();
+            ctx.push_log(Record { level, msg, fields });
+            Ok(())
+        },
+    )?;
 
-    vm.add_func("motor", "forward", |mut bot: Caller| {
+    let motor = module("motor");
+    vm.add_func(&motor, "forward", |bot: Caller| {
         bot.state_mut().action = Action::MotorForward
     })?
-    .add_func("motor", "left", |mut bot: Caller| {
+    .add_func(&motor, "left", |bot: Caller| {
         bot.state_mut().action = Action::MotorLeft
     })?
-    .add_func("motor", "right", |mut bot: Caller| {
+    .add_func(&motor, "right", |bot: Caller| {
         bot.state_mut().action = Action::MotorRight
     })?;
 
-    vm.add_func("sensors", "contact", |bot: Caller| {
-        !bot.state().front.is_empty() as i32
+    let sensors = module("sensors");
+    if abi == 0 {
+        vm.add_func(&sensors, "contact", |bot: Caller| {
+            !bot.state().front.is_empty() as i32
+        })?;
+    } else {
+        vm.add_func(&sensors, "contact", |bot: Caller| match bot.state().front {
+            Cell::Ground => CONTACT_NONE,
+            Cell::Wall => CONTACT_WALL,
+            Cell::Bot(_) => CONTACT_BOT,
+        })?;
+    }
+
+    vm.add_func(&module("sys"), "random", |bot: Caller| {
+        bot.consume_fuel(RANDOM_FUEL)?;
+        Ok(bot.state_mut().rng.next_u64())
     })?;
 
-    Ok(vm)
+    let storage = module("storage");
+    vm.add_func(
+        &storage,
+        "get",
+        |bot: Caller, key: u32, key_len: u32, ptr: u32, cap: u32| {
+            bot.consume_fuel(STORAGE_FUEL_BASE + key_len as u64 * STORAGE_FUEL_RATIO)?;
+            if key_len as usize > MAX_KEY_LEN {
+                return Ok(-1);
+            }
+            let key = with_mem(bot, key, key_len)?.0.to_vec();
+            let (out, ctx) = with_mem(bot, ptr, cap)?;
+            let len = ctx.state().storage.get(&key, |v| {
+                v.map(|v| {
+                    let n = v.len().min(out.len());
+                    out[..n].copy_from_slice(&v[..n]);
+                    v.len()
+                })
+            });
+            let Some(len) = len else {
+                return Ok(-1);
+            };
+            bot.consume_fuel(len.min(cap as usize) as u64 * STORAGE_FUEL_RATIO)?;
+            Ok(len as i32)
+        },
+    )?
+    .add_func(
+        &storage,
+        "set",
+        |bot: Caller, key: u32, key_len: u32, ptr: u32, len: u32| {
+            bot.consume_fuel(
+                STORAGE_FUEL_BASE + (key_len as u64 + len as u64) * STORAGE_FUEL_RATIO,
+            )?;
+            let key = with_mem(bot, key, key_len)?.0.to_vec();
+            let (value, ctx) = with_mem(bot, ptr, len)?;
+            Ok(if ctx.state_mut().storage.set(&key, value) {
+                0
+            } else {
+                -1
+            })
+        },
+    )?
+    .add_func(&storage, "delete", |bot: Caller, key: u32, key_len: u32| {
+        bot.consume_fuel(STORAGE_FUEL_BASE + key_len as u64 * STORAGE_FUEL_RATIO)?;
+        let (key, ctx) = with_mem(bot, key, key_len)?;
+        Ok(ctx.state_mut().storage.delete(key) as i32)
+    })?;
+
+    Ok(())
+}
+
+/// Registers host functions counting calls, and recording them for traced bots
+struct Instrumented<'a>(&'a mut VM);
+impl Instrumented<'_> {
+    fn add_func<P, R, F: HostFunc<bot::Store, P, R>>(
+        &mut self,
+        module: &str,
+        name: &str,
+        func: F,
+    ) -> Result<&mut Self> {
+        let call = Str::from(format!("{}.{}", wasm::spec::split_version(module).0, name));
+        let func = func.into_dyn();
+        self.0.add_dyn_func(
+            module,
+            name,
+            F::signature(),
+            Box::new(move |bot, params, results| {
+                let calls = &mut bot.state_mut().calls;
+                match calls.get_mut(&call) {
+                    Some(n) => *n += 1,
+                    None => _ = calls.insert(call.clone(), 1),
+                }
+                match &bot.state().trace {
+                    Some(trace) if trace.len() < bot::MAX_TRACE_CALLS => {}
+                    Some(_) => {
+                        bot.state_mut().trace_skipped += 1;
+                        return func(bot, params, results);
+                    }
+                    None => return func(bot, params, results),
+                }
+                let fuel = bot.consume_fuel(0).unwrap_or(0);
+                let res = func(bot, params, results);
+                let call = HostCall {
+                    func: call.clone(),
+                    args: values(params),
+                    ret: res.as_ref().map_or(Vec::new(), |_| values(results)),
+                    fuel: fuel.saturating_sub(bot.consume_fuel(0).unwrap_or(0)),
+                    trap: res.is_err(),
+                };
+                if let Some(trace) = &mut bot.state_mut().trace {
+                    trace.push(call);
+                }
+                res
+            }),
+        )?;
+        Ok(self)
+    }
+}
+fn values(vs: &[Value]) -> Vec<i64> {
+    vs.iter()
+        .map(|v| match *v {
+            Value::I32(v) => v.into(),
+            Value::I64(v) => v,
+        })
+        .collect()
+}
+
+/// Host functions of an api version as `(module, name, signature)`, to check bindings
+pub fn host_funcs(abi: u32) -> Result<Vec<(String, String, String)>> {
+    let vm = new_vm()?;
+    let imports = vm.imports();
+    let mut funcs: Vec<_> = imports
+        .iter()
+        .filter(|(module, _, _)| imports.is_versioned(module))
+        .filter_map(|(module, name, sig)| {
+            let (module, v) = wasm::spec::split_version(module);
+            (v == abi).then(|| (module.to_owned(), name.to_owned(), sig.to_string()))
+        })
+        .collect();
+    funcs.sort();
+    Ok(funcs)
 }
 
-type Caller<'a> = wasm::Caller<'a, bot::Store>;
+type Caller<'a> = &'a mut dyn wasm::spec::Caller<bot::Store>;
 
-fn with_mem<'a>(
-    caller: &'a mut Caller,
+pub fn with_mem<S>(
+    caller: &mut dyn wasm::spec::Caller<S>,
     ptr: u32,
     len: u32,
-) -> Result<(&'a mut [u8], &'a mut bot::Store)> {
-    let (data, ctx) = caller.memory()?.data_and_store_mut(caller);
+) -> Result<(&mut [u8], &mut S)> {
+    let (data, ctx) = caller.memory()?;
     let ptr = ptr as usize;
     let len = len as usize;
     let mem = data
@@ -64,5 +269,80 @@ fn with_mem<'a>(
 #[cold]
 #[inline]
 pub fn err_wrap(ctx: &'static str, err: wasm::Error) -> Error {
-    Error::new(ctx, format!("{:#}", err))
+    let (msg, frames) = wasm::split_backtrace(&err);
+    let mut err = Error::new(ctx, msg);
+    err.frames = frames
+        .into_iter()
+        .map(|frame| Frame {
+            func: frame.func,
+            name: frame.name.map(Str::from),
+            file: frame.file.map(Str::from),
+            line: frame.line,
+            col: frame.column,
+        })
+        .collect();
+    err
+}
+
+pub fn log_record(record: Record) -> LogRecord {
+    LogRecord {
+        level: match record.level {
+            Level::Debug => LogLevel::Debug,
+            Level::Info => LogLevel::Info,
+            Level::Warn => LogLevel::Warn,
+            Level::Error => LogLevel::Error,
+        },
+        msg: record.msg.into(),
+        fields: (record.fields.into_iter())
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect(),
+    }
+}
+
+#[cold]
+pub fn compile_err(err: wasm::Error) -> CompileErr {
+    let issues = err
+        .downcast_ref::<wasm::spec::LinkError>()
+        .map(|link| link.0.iter().map(link_issue).collect())
+        .unwrap_or_default();
+    CompileErr {
+        err: Error::new("Failed to compile", err.root_cause().to_string()),
+        issues,
+    }
+}
+fn link_issue(issue: &wasm::spec::LinkIssue) -> LinkIssue {
+    use wasm::spec::LinkIssue as L;
+    let sig = |sig: &wasm::spec::Signature| Str::from(sig.to_string());
+    match issue {
+        L::UnknownImport { module, name } => LinkIssue::UnknownImport {
+            module: module.clone().into(),
+            name: name.clone().into(),
+        },
+        L::ImportSignature {
+            module,
+            name,
+            expected,
+            found,
+        } => LinkIssue::ImportSignature {
+            module: module.clone().into(),
+            name: name.clone().into(),
+            expected: sig(expected),
+            found: found.as_ref().map(sig),
+        },
+        L::MixedVersions { versions } => LinkIssue::MixedVersions {
+            versions: versions.clone(),
+        },
+        L::MissingExport { name } => LinkIssue::MissingExport {
+            name: (*name).into(),
+        },
+        L::ExportSignature {
+            name,
+            expected,
+            found,
+        } => LinkIssue::ExportSignature {
+            name: (*name).into(),
+            expected: sig(expected),
+            found: found.as_ref().map(sig),
+        },
+    }
 }
@@ -1,16 +1,27 @@
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 
+use super::api::{ON_BOOT, ON_COLLIDE, WASI_TICK_NS};
 use super::gen;
+use super::storage::Storage;
+use super::wasm::{self, spec::Instance as _};
 use bulb::{
-    dto::{BotId, BotSrc, Cell, CellMap, ProgramId},
+    dto::{
+        BotId, BotInfo, BotSrc, Cell, CellMap, CrashPolicy, Event, HostCall, PlayerId, ProgramId,
+        Stats, Str,
+    },
     hex::{Direction, Hex},
 };
-use sys::wasm::{self, spec::StoreRef};
+use sys::rng::Rng;
 use tracing::instrument;
 
 pub struct Bot {
     pub program: ProgramId,
+    /// Scenario owner
+    pub player: Option<PlayerId>,
     pub cpu: Result<Cpu, StateOff>,
+    pub crashes: Crashes,
+    pub stats: Stats,
 }
 impl Bot {
     pub fn at(&self) -> Hex {
@@ -25,32 +36,148 @@ impl Bot {
             Err(off) => BotSrc { bid, at: off.at },
         }
     }
+
+    pub fn info(&self, bid: BotId) -> BotInfo {
+        let (at, facing) = match &self.cpu {
+            Ok(cpu) => (cpu.state().at, cpu.state().facing),
+            Err(off) => (off.at, off.facing),
+        };
+        BotInfo {
+            bid,
+            pid: self.program,
+            player: self.player,
+            at,
+            facing,
+            running: self.cpu.is_ok(),
+        }
+    }
+
+    pub fn set_at(&mut self, at: Hex) {
+        match &mut self.cpu {
+            Ok(cpu) => cpu.state_mut().at = at,
+            Err(off) => off.at = at,
+        }
+    }
+    pub fn set_facing(&mut self, facing: Direction) {
+        match &mut self.cpu {
+            Ok(cpu) => cpu.state_mut().facing = facing,
+            Err(off) => off.facing = facing,
+        }
+    }
+    pub fn add_fuel(&mut self, fuel: u64) -> Result<(), std::num::TryFromIntError> {
+        match &mut self.cpu {
+            Ok(cpu) => cpu.process.add_fuel(fuel),
+            Err(off) => {
+                off.fuel = off.fuel.saturating_add(fuel);
+                Ok(())
+            }
+        }
+    }
+
+    /// Stopped by crash policy for now
+    pub fn is_asleep(&self, tick: u32) -> bool {
+        self.crashes.halted || tick < self.crashes.wake
+    }
+    /// Count an error and apply policy once too many
+    pub fn crash(&mut self, bid: BotId, policy: &CrashPolicy, tick: u32) -> Option<Event> {
+        self.stats.errors += 1;
+        self.crashes.errors += 1;
+        if self.crashes.errors < policy.errors() {
+            return None;
+        }
+        self.crashes.errors = 0;
+        let src = self.src(bid);
+        if let Ok(cpu) = &mut self.cpu {
+            let fuel = cpu.process.fuel();
+            self.cpu = Err(cpu.state().shutdown(fuel));
+        }
+        let (off, crashes) = (self.cpu.as_mut().err().unwrap(), &mut self.crashes);
+        Some(match *policy {
+            CrashPolicy::Halt { .. } => {
+                crashes.halted = true;
+                Event::BotHalt { src }
+            }
+            CrashPolicy::Reboot {
+                backoff,
+                max_backoff,
+                ..
+            } => {
+                let delay = backoff
+                    .saturating_mul(1 << crashes.reboots.min(31))
+                    .min(max_backoff);
+                crashes.reboots += 1;
+                crashes.wake = tick.saturating_add(delay);
+                Event::BotReboot { src, delay }
+            }
+            CrashPolicy::Kill { .. } => {
+                off.fuel = 0;
+                Event::BotKill { src }
+            }
+        })
+    }
+}
+
+/// Errors tracking for [`CrashPolicy`]
+#[derive(Default)]
+pub struct Crashes {
+    /// Consecutive errors, reset by a successful tick
+    pub errors: u32,
+    reboots: u32,
+    halted: bool,
+    /// Tick to reboot at
+    wake: u32,
 }
 
 pub struct Cpu {
     pub process: wasm::Instance<Store>,
     tick: wasm::Func<(), ()>,
+    on_collide: Option<wasm::Func<(), ()>>,
 }
 impl Cpu {
     #[cold]
     #[inline]
     #[instrument(level = "trace", skip_all)]
     pub fn boot(
-        tpl: &mut Template,
+        tpl: &Template,
         state: State,
         fuel: u64,
-    ) -> Result<Self, (String, wasm::Error)> {
-        let (mut process, res) = wasm::Instance::started(tpl, state, fuel);
-        if let Err(err) = res {
+        wasi: &wasm::WasiProfile,
+        tick: u32,
+    ) -> Result<Self, (Vec<sys::log::Record>, wasm::Error)> {
+        let mut process = wasm::Instance::new(tpl, state, fuel).map_err(|err| (Vec::new(), err))?;
+        process.store_mut().set_profile(wasi);
+        process.store_mut().set_tick(tick.into());
+        if let Err(err) = process.start() {
             return Err((process.store_mut().read_log(), err));
         }
-        let tick = process.get_func::<(), ()>("tick").unwrap();
-        Ok(Self { process, tick })
+        let tick = process
+            .get_func::<(), ()>("tick")
+            .map_err(|err| (Vec::new(), err))?;
+        if let Ok(on_boot) = process.get_func::<(), ()>(ON_BOOT) {
+            if let Err(err) = process.call(&on_boot, ()) {
+                return Err((process.store_mut().read_log(), err));
+            }
+        }
+        let on_collide = process.get_func(ON_COLLIDE).ok();
+        Ok(Self {
+            process,
+            tick,
+            on_collide,
+        })
     }
     #[inline]
-    pub fn tick(&mut self) -> Result<(), wasm::Error> {
+    pub fn tick(&mut self, tick: u32) -> Result<(), wasm::Error> {
+        self.process.store_mut().set_tick(tick.into());
         self.process.call(&self.tick, ())
     }
+    /// Call `on_collide` if exported
+    #[inline]
+    pub fn on_collide(&mut self) -> Result<(), wasm::Error> {
+        match &self.on_collide {
+            Some(f) => self.process.call(f, ()),
+            None => Ok(()),
+        }
+    }
     #[inline]
     pub fn store(&self) -> &Store {
         self.process.store()
@@ -81,14 +208,29 @@ pub struct State {
     pub front: Cell,
     /// Next action intent
     pub action: Action,
+    /// Generator of `sys.random`
+    pub rng: Rng,
+    /// Program storage
+    pub storage: Storage,
+    /// Host calls, only recorded for the debugged bot
+    pub trace: Option<Vec<HostCall>>,
+    /// Host calls not recorded once [`MAX_TRACE_CALLS`] is reached
+    pub trace_skipped: u32,
+    /// Host calls count by name since last [`State::take_calls`]
+    pub calls: BTreeMap<Str, u64>,
 }
 pub type Store = wasm::WasiStore<State>;
+/// Host calls recorded per export call of the debugged bot
+pub const MAX_TRACE_CALLS: usize = 256;
 impl State {
-    pub fn boot(id: BotId, off: &StateOff) -> Self {
+    pub fn boot(id: BotId, off: &StateOff, world_seed: u32, storage: Storage) -> Self {
         Self {
             id,
+            storage,
             at: off.at,
             facing: off.facing,
+            // Distinct from wasi stream
+            rng: Rng::new(Rng::mix(seed(world_seed, id), 1)),
             ..Default::default()
         }
     }
@@ -110,6 +252,24 @@ impl State {
         }
     }
 
+    /// Start or stop recording host calls
+    pub fn set_traced(&mut self, traced: bool) {
+        if traced != self.trace.is_some() {
+            self.trace = traced.then(Vec::new);
+        }
+    }
+    /// Recorded host calls and count of skipped ones
+    pub fn take_trace(&mut self) -> (Vec<HostCall>, u32) {
+        let calls = self.trace.as_mut().map(std::mem::take).unwrap_or_default();
+        (calls, std::mem::take(&mut self.trace_skipped))
+    }
+    /// Move host calls count into stats
+    pub fn take_calls(&mut self, stats: &mut Stats) {
+        for (name, n) in std::mem::take(&mut self.calls) {
+            *stats.calls.entry(name).or_default() += n;
+        }
+    }
+
     pub fn update(&mut self, map: &impl CellMap) {
         self.action = Self::default().action;
         self.front = map.get(self.at_front());
@@ -124,6 +284,11 @@ impl Default for State {
             facing: Direction::Up,
             front: Cell::Ground,
             action: Action::Wait,
+            rng: Rng::new(0),
+            storage: Storage::default(),
+            trace: None,
+            trace_skipped: 0,
+            calls: BTreeMap::new(),
         }
     }
 }
@@ -151,6 +316,18 @@ pub enum Action {
 
 pub type Template = wasm::Template<Store>;
 
+/// Randomness of a bot only depends on world and id
+fn seed(world_seed: u32, id: BotId) -> u64 {
+    Rng::mix(world_seed.into(), id.into())
+}
+/// Deterministic wasi with randomness seeded by world and bot
+pub fn wasi_profile(world_seed: u32, id: BotId) -> wasm::WasiProfile {
+    wasm::WasiProfile {
+        seed: Some(seed(world_seed, id)),
+        tick_ns: Some(WASI_TICK_NS),
+    }
+}
+
 impl From<gen::Id> for BotId {
     fn from(id: gen::Id) -> Self {
         Self::from(id.pack())
@@ -0,0 +1,132 @@
+//! Program compilation outside of game thread
+
+use super::api::VM;
+use super::bot;
+use super::wasm::spec::Linker as _;
+use bulb::dto::{Bytes, CompileRes, Error, Promise, UserId};
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use sys::Result;
+
+/// Max pending compilations by user
+pub const MAX_USER_QUEUE: usize = 2;
+const MAX_WORKERS: usize = 4;
+
+/// Destination of a compiled program
+pub enum Target {
+    User(UserId, Promise<CompileRes>),
+    /// Program index of the scenario being loaded with given id
+    Scenario {
+        load: u32,
+        index: usize,
+    },
+}
+
+struct Job {
+    code: Bytes,
+    target: Target,
+}
+pub struct Compiled {
+    pub code: Bytes,
+    pub res: Result<bot::Template>,
+    pub target: Target,
+}
+
+pub struct Compiler {
+    jobs: mpsc::Sender<Job>,
+    done: mpsc::Receiver<Compiled>,
+    pending: HashMap<UserId, usize>,
+    /// Compile on caller thread when workers are unavailable (wasm32)
+    inline: Option<(Arc<VM>, mpsc::Sender<Compiled>)>,
+}
+impl Compiler {
+    pub fn new(vm: Arc<VM>) -> Self {
+        let (jobs, jobs_rx) = mpsc::channel::<Job>();
+        let (done_tx, done) = mpsc::channel();
+        let jobs_rx = Arc::new(Mutex::new(jobs_rx));
+        let workers = thread::available_parallelism().map_or(1, |n| n.get().min(MAX_WORKERS));
+        let mut spawned = 0;
+        for i in 0..workers {
+            let jobs_rx = jobs_rx.clone();
+            let done_tx = done_tx.clone();
+            let vm = vm.clone();
+            let res = thread::Builder::new()
+                .name(format!("compiler-{}", i))
+                .spawn(move || loop {
+                    let job = jobs_rx.lock().unwrap().recv();
+                    let Ok(job) = job else {
+                        return;
+                    };
+                    if done_tx.send(compile(&vm, job)).is_err() {
+                        return;
+                    }
+                });
+            match res {
+                Ok(_) => spawned += 1,
+                Err(err) => {
+                    tracing::debug!("no compiler thread: {}", err);
+                    break;
+                }
+            }
+        }
+        Self {
+            jobs,
+            done,
+            pending: HashMap::new(),
+            inline: (spawned == 0).then_some((vm, done_tx)),
+        }
+    }
+
+    /// Queue compilation or reject it if user has too many pending ones
+    pub fn submit(&mut self, code: Bytes, user: UserId, cb: Promise<CompileRes>) {
+        let pending = self.pending.entry(user.clone()).or_default();
+        if *pending >= MAX_USER_QUEUE {
+            tracing::debug!(%user, "compile queue full");
+            return cb.resolve(Err(Error::new(
+                "Failed to compile",
+                format!("Too many pending compilations (max {})", MAX_USER_QUEUE),
+            )
+            .into()));
+        }
+        *pending += 1;
+        self.send(Job {
+            code,
+            target: Target::User(user, cb),
+        });
+    }
+    /// Queue compilation of a scenario program, not limited
+    pub fn submit_scenario(&mut self, code: Bytes, load: u32, index: usize) {
+        self.send(Job {
+            code,
+            target: Target::Scenario { load, index },
+        });
+    }
+    fn send(&mut self, job: Job) {
+        if let Some((vm, done)) = &self.inline {
+            _ = done.send(compile(vm, job));
+        } else {
+            _ = self.jobs.send(job);
+        }
+    }
+
+    /// Finished compilations
+    pub fn poll(&mut self) -> Option<Compiled> {
+        let compiled = self.done.try_recv().ok()?;
+        if let Target::User(user, _) = &compiled.target {
+            if let Some(pending) = self.pending.get_mut(user) {
+                *pending -= 1;
+                if *pending == 0 {
+                    self.pending.remove(user);
+                }
+            }
+        }
+        Some(compiled)
+    }
+}
+
+fn compile(vm: &VM, Job { code, target }: Job) -> Compiled {
+    let span = tracing::trace_span!("compile", len = code.len());
+    let res = span.in_scope(|| vm.link(&code));
+    Compiled { code, res, target }
+}
@@ -0,0 +1,46 @@
+//! Step-through debugging of a single bot
+
+use bulb::dto::{BotId, BotSrc, Breakpoint, DebugBody, Event};
+
+pub struct Debugger {
+    /// Traced bot
+    pub bid: BotId,
+    breakpoints: Vec<Breakpoint>,
+    hit: Option<(BotSrc, Breakpoint)>,
+}
+impl Debugger {
+    pub fn new(body: DebugBody) -> Self {
+        Self {
+            bid: body.bid,
+            breakpoints: body.breakpoints,
+            hit: None,
+        }
+    }
+
+    /// Remember first breakpoint matching an event of traced bot
+    pub fn watch(&mut self, event: &Event) {
+        if self.hit.is_some() {
+            return;
+        }
+        let Some(src) = event.src().filter(|src| src.bid == self.bid) else {
+            return;
+        };
+        if let Some(hit) = self.breakpoints.iter().find(|b| matches(b, event)) {
+            self.hit = Some((*src, hit.clone()));
+        }
+    }
+    /// Breakpoint hit since last call
+    #[inline]
+    pub fn take_hit(&mut self) -> Option<(BotSrc, Breakpoint)> {
+        self.hit.take()
+    }
+}
+
+fn matches(breakpoint: &Breakpoint, event: &Event) -> bool {
+    match (breakpoint, event) {
+        (Breakpoint::Collide, Event::BotCollide { .. }) => true,
+        (Breakpoint::Error, Event::BotError { .. }) => true,
+        (Breakpoint::Call { func }, Event::BotTrace { call, .. }) => call.func == *func,
+        _ => false,
+    }
+}
@@ -129,6 +129,23 @@ impl<K: Into<Id> + From<Id>, V> Array<K, V> {
                     .map(|val| (K::from(Id { index, gen: *gen }), val))
             })
     }
+    #[cfg(feature = "parallel")]
+    pub fn par_iter_mut(
+        &mut self,
+    ) -> impl rayon::iter::IndexedParallelIterator<Item = Option<(K, &mut V)>>
+    where
+        K: Send,
+        V: Send,
+    {
+        use rayon::prelude::*;
+        self.vals
+            .par_iter_mut()
+            .zip(self.gens.par_iter().enumerate())
+            .map(|(opt, (index, gen))| {
+                opt.as_mut()
+                    .map(|val| (K::from(Id { index, gen: *gen }), val))
+            })
+    }
     /// To use with split_at_mut
     pub fn iter_index(&self) -> std::ops::Range<usize> {
         0..self.vals.len()
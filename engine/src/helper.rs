@@ -1,17 +1,23 @@
-use crate::Game;
-use bulb::dto::{Command, Event, State};
+use crate::{Game, MAX_TICK_DURATION_MS};
+use bulb::dto::{AdminRes, Command, Event, Promise, ReplyErr, State};
+use std::collections::VecDeque;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
 
 pub struct GameState<R, S> {
     game: Game<S>,
     commands: R,
     state: State,
+    tick_ms: u64,
+    /// Pending single ticks while paused
+    steps: VecDeque<Promise<AdminRes>>,
 }
 impl<R, S> GameState<R, S>
 where
     R: FnMut() -> Option<Command>,
     S: FnMut(Event) -> (),
 {
-    pub fn new(commands: R, events: S, paused: bool) -> Self {
+    pub fn new(commands: R, events: S, paused: bool, tick_ms: u64) -> Self {
         let state = if paused {
             tracing::warn!("game is paused");
             State::Paused
@@ -22,22 +28,89 @@ where
             game: Game::new(events),
             commands,
             state,
+            tick_ms: tick_ms.clamp(1, MAX_TICK_DURATION_MS),
+            steps: VecDeque::new(),
         }
     }
 
+    /// Milliseconds to wait between updates
+    #[inline]
+    pub fn tick_ms(&self) -> u64 {
+        self.tick_ms
+    }
+
+    /// Apply a command, returns `true` if an update is due now
+    pub fn apply(&mut self, cmd: Command) -> bool {
+        match cmd {
+            Command::ChangeState(state, done) => {
+                let changed = state != self.state;
+                if changed {
+                    self.state = state;
+                    for step in self.steps.drain(..) {
+                        step.resolve(Err(ReplyErr::NotPaused));
+                    }
+                    self.game.send(Event::StateChange(self.state));
+                }
+                done.resolve(Ok(()));
+                return changed;
+            }
+            Command::SetTickMs(ms, done) => {
+                self.tick_ms = ms.clamp(1, MAX_TICK_DURATION_MS);
+                self.game.send(Event::TickRateChange { ms: self.tick_ms });
+                done.resolve(Ok(()));
+            }
+            Command::Step(done) if self.state == State::Paused => {
+                self.steps.push_back(done);
+                return true;
+            }
+            Command::Step(done) => done.resolve(Err(ReplyErr::NotPaused)),
+            cmd => self.game.apply(cmd),
+        }
+        false
+    }
+
     pub fn update(&mut self) -> State {
-        let prev_state = self.state;
         while let Some(cmd) = (self.commands)() {
-            if let Command::ChangeState(state) = cmd {
-                self.state = state;
-            } else {
-                self.game.apply(cmd);
-            }
+            self.apply(cmd);
         }
-        if self.state != prev_state {
+        self.game.poll_compiled();
+        let step = match self.state {
+            State::Running => None,
+            State::Paused if !self.steps.is_empty() => self.steps.pop_front(),
+            _ => return self.state,
+        };
+        self.game.tick();
+        if let Some(step) = step {
+            step.resolve(Ok(()));
+        }
+        if self.game.take_break() && self.state == State::Running {
+            self.state = State::Paused;
+            self.game.send(Event::StateChange(self.state));
+        }
+        if self.game.is_over() && self.state == State::Running {
+            self.state = State::Paused;
             self.game.send(Event::StateChange(self.state));
         }
-        self.game.tick();
         self.state
     }
+
+    /// Update until stopped, applying commands from `recv` while waiting for next tick
+    ///
+    /// `recv` blocks up to given duration like [`std::sync::mpsc::Receiver::recv_timeout`].
+    pub fn run(&mut self, mut recv: impl FnMut(Duration) -> Result<Command, RecvTimeoutError>) {
+        while self.update() != State::Stopped {
+            let next = Instant::now() + Duration::from_millis(self.tick_ms);
+            while let Some(timeout) = next.checked_duration_since(Instant::now()) {
+                match recv(timeout) {
+                    Ok(cmd) => {
+                        if self.apply(cmd) {
+                            break;
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+        }
+    }
 }
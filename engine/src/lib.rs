@@ -1,20 +1,39 @@
 mod api;
 mod bot;
+mod compiler;
+mod debug;
 mod gen;
 mod helper;
+mod master;
 mod noise;
+mod objective;
+mod storage;
 use api::*;
+pub use api::{host_funcs, ABI};
 use bot::Bot;
 pub use bulb::dto::{Event::*, *};
-use bulb::hex::{Angle, Hex};
+use bulb::hex::{Angle, Direction, Hex};
+pub use bulb::scenario;
 use chrono::Utc;
 pub use helper::*;
 use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+#[cfg(all(feature = "interpreter", not(feature = "mock")))]
+use sys::interpreter::wasm;
+#[cfg(feature = "mock")]
+use sys::mock::wasm;
+#[cfg(not(any(feature = "interpreter", feature = "mock")))]
+use sys::wasm;
 use sys::Result;
 use tracing::instrument;
 use typed_index_collections::TiVec;
+use wasm::spec::Instance as _;
 
 pub const DEFAULT_TICK_DURATION_MS: u64 = 1000;
+/// Longest interval between ticks accepted by [`GameState`]
+pub const MAX_TICK_DURATION_MS: u64 = 60_000;
+/// Ticks between [`Event::MetricsSummary`]
+pub const METRICS_PERIOD: u32 = 60;
 
 pub struct Game<S> {
     events: EventSender<S>,
@@ -22,75 +41,193 @@ pub struct Game<S> {
     counter: u32,
     in_tick: bool,
 
-    vm: VM,
+    compiler: compiler::Compiler,
     programs: Programs,
     bots: Bots,
 
     map: GameMap,
     cache: GameCache,
+
+    scenario: Option<objective::Progress>,
+    /// Scenario waiting for its programs to compile
+    loading: Option<Loading>,
+    /// Id of last scenario load
+    loads: u32,
+    master: Option<master::Master>,
+    over: bool,
+    crash_policy: CrashPolicy,
+    /// Debugged bot hit a breakpoint during last tick
+    hit_break: bool,
 }
 impl<S: FnMut(Event)> Game<S> {
     pub fn new(events: S) -> Self {
         Self {
-            events: EventSender(events),
+            events: EventSender(events, None),
             counter: 0,
             in_tick: false,
-            vm: new_vm().unwrap(),
+            compiler: compiler::Compiler::new(Arc::new(new_vm().unwrap())),
             programs: TiVec::new(),
             bots: gen::Array::new(),
             map: GameMap::new(42),
             cache: GameCache::new(),
+            scenario: None,
+            loading: None,
+            loads: 0,
+            master: None,
+            over: false,
+            crash_policy: CrashPolicy::default(),
+            hit_break: false,
         }
     }
 
     #[instrument(skip_all, fields(id = self.counter))]
     pub fn tick(&mut self) {
         self.with_tick();
+        self.poll_compiled();
+        self.tick_master();
 
-        for (id, bot) in self.bots.iter_mut() {
-            //Process
-            Self::tick_bot(
-                id,
-                bot,
-                &mut self.programs,
-                &self.vm,
-                &self.map,
-                &mut self.events,
-            )
+        let ctx = TickCtx {
+            programs: &self.programs,
+            map: &self.map,
+            tick: self.counter,
+            policy: &self.crash_policy,
+            traced: self.events.1.as_ref().map(|debug| debug.bid),
+        };
+        let process = |(id, bot): (BotId, &mut Bot)| {
+            // Buffered to keep a stable order
+            let mut buf = Vec::new();
+            Self::tick_bot(id, bot, &ctx, &mut EventSender(|e| buf.push(e), None));
+            buf
+        };
+        #[cfg(feature = "parallel")]
+        let buffers: Vec<Vec<Event>> = {
+            use rayon::prelude::*;
+            self.bots
+                .par_iter_mut()
+                .map(|opt| opt.map(process).unwrap_or_default())
+                .collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let buffers: Vec<Vec<Event>> = self.bots.iter_mut().map(process).collect();
+        for event in buffers.into_iter().flatten() {
+            self.events.send(event);
         }
         self.tick_act();
         self.tick_death();
         self.tick_move();
+        if let Some(scenario) = self.scenario.as_mut().filter(|_| !self.over) {
+            scenario.update(self.counter, &self.bots, &self.map, &mut self.events);
+            self.over = scenario.is_over();
+        }
+        if self.counter.is_multiple_of(METRICS_PERIOD) && !self.programs.is_empty() {
+            let programs = self.metrics(false).programs;
+            self.events.send(MetricsSummary { programs });
+        }
+        if let Some((src, hit)) = self.events.1.as_mut().and_then(|debug| debug.take_hit()) {
+            self.events.send(BotBreak { src, hit });
+            self.hit_break = true;
+        }
 
         self.events.send(TickEnd);
         tracing::debug!("done");
         self.in_tick = false;
         self.counter += 1;
     }
+    /// Register programs compiled by workers, also needed while paused
+    pub fn poll_compiled(&mut self) {
+        while let Some(compiled) = self.compiler.poll() {
+            match compiled.target {
+                compiler::Target::User(_, cb) => {
+                    let res = compiled
+                        .res
+                        .map(|tpl| {
+                            self.programs
+                                .push_and_get_key(Program::compiled(compiled.code, tpl))
+                        })
+                        .map_err(compile_err);
+                    cb.resolve(res)
+                }
+                compiler::Target::Scenario { load, index } => {
+                    self.scenario_compiled(load, index, compiled.code, compiled.res)
+                }
+            }
+        }
+    }
+    /// Apply game master orders
+    fn tick_master(&mut self) {
+        let Some(master) = &mut self.master else {
+            return;
+        };
+        let (orders, res) = master.tick(self.counter);
+        if let Err(err) = res {
+            tracing::error!("game master: {:#}", err);
+        }
+        for order in orders {
+            tracing::debug!(?order);
+            match order {
+                master::Order::Spawn { pid, at, facing } => {
+                    if let Err(err) = self.spawn(pid, at, facing, SPAWN_FUEL, None) {
+                        self.events.send(SpawnError { pid, to: at, err });
+                    }
+                }
+                master::Order::Set { at, cell } => {
+                    if let Cell::Bot(_) = self.map.get(at) {
+                        tracing::warn!("cannot set {:?} with bot", at);
+                        continue;
+                    }
+                    self.map.set(at, cell);
+                    self.events.send(Cells(CellRange::new(
+                        HexRange { center: at, rad: 0 },
+                        &self.map,
+                    )));
+                }
+                master::Order::Announce(msg) => self.events.send(Announce { msg: msg.into() }),
+                master::Order::End { winner } => {
+                    if !self.over {
+                        self.over = true;
+                        self.events.send(GameOver { winner });
+                    }
+                }
+            }
+        }
+    }
     #[inline]
     #[instrument(level = "debug", name = "bot", skip_all, fields(id = gen::I::from(id)))]
-    fn tick_bot(
+    fn tick_bot<E: FnMut(Event)>(
         id: BotId,
         bot: &mut Bot,
-        programs: &mut Programs,
-        vm: &VM,
-        map: &GameMap,
-        events: &mut EventSender<S>,
+        ctx: &TickCtx,
+        events: &mut EventSender<E>,
     ) {
+        let TickCtx {
+            programs,
+            map,
+            tick,
+            policy,
+            traced,
+        } = *ctx;
+        let asleep = bot.is_asleep(tick);
         match bot.cpu.as_mut() {
-            Ok(cpu) => cpu.state_mut().update(map),
+            Ok(cpu) => {
+                cpu.state_mut().set_traced(traced == Some(id));
+                cpu.state_mut().update(map)
+            }
             Err(off) => {
-                off.fuel -= 1;
-                //TODO: if sleep return
-                if off.fuel < MIN_BOOT_FUEL {
+                // Killed bots may be at zero until removed
+                off.fuel = off.fuel.saturating_sub(1);
+                if asleep || off.fuel < MIN_BOOT_FUEL {
                     return;
                 }
 
-                let mut state = bot::State::boot(id, off);
+                let bucket = programs[bot.program].storage[&bot.player].clone();
+                let storage = storage::Storage::new(bucket);
+                let mut state = bot::State::boot(id, off, map.seed, storage);
                 let src = state.src();
+                state.set_traced(traced == Some(id));
                 state.update(map);
-                let tpl = programs[bot.program].compiled(vm);
-                match bot::Cpu::boot(tpl.unwrap(), state, off.fuel) {
+                let tpl = programs[bot.program].template();
+                let wasi = bot::wasi_profile(map.seed, id);
+                match bot::Cpu::boot(tpl, state, off.fuel, &wasi, tick) {
                     Ok(cpu) => bot.cpu = Ok(cpu),
                     Err((log, err)) => {
                         events.log(src, log);
@@ -98,6 +235,9 @@ impl<S: FnMut(Event)> Game<S> {
                             src,
                             err: err_wrap("During start", err),
                         });
+                        if let Some(event) = bot.crash(id, policy, tick) {
+                            events.send(event);
+                        }
                         return;
                     }
                 }
@@ -107,14 +247,27 @@ impl<S: FnMut(Event)> Game<S> {
 
         // Tick
         let src = cpu.state().src();
-        tracing::trace!("fuel {}", cpu.process.fuel());
-        let res = cpu.tick();
+        let fuel = cpu.process.fuel();
+        tracing::trace!("fuel {}", fuel);
+        let res = cpu.tick(tick);
+        let used = fuel.saturating_sub(cpu.process.fuel());
+        bot.stats.ticks += 1;
+        bot.stats.fuel += used;
+        bot.stats.max_fuel = bot.stats.max_fuel.max(used);
+        cpu.state_mut().take_calls(&mut bot.stats);
+        events.trace(src, cpu.state_mut().take_trace());
         events.log(src, cpu.store_mut().read_log());
-        if let Err(err) = res {
-            events.send(BotError {
-                src,
-                err: err_wrap("During tick", err),
-            });
+        match res {
+            Ok(()) => bot.crashes.errors = 0,
+            Err(err) => {
+                events.send(BotError {
+                    src,
+                    err: err_wrap("During tick", err),
+                });
+                if let Some(event) = bot.crash(id, policy, tick) {
+                    events.send(event);
+                }
+            }
         }
     }
     /// Edit bots and maps
@@ -124,9 +277,10 @@ impl<S: FnMut(Event)> Game<S> {
         self.cache.moves.clear();
         self.cache.deaths.clear();
         for index in self.bots.iter_index() {
-            if let Some((id, bot, others)) = self.bots.split_at_mut(index) {
+            if let Some((id, bot, _)) = self.bots.split_at_mut(index) {
                 let alive = match &mut bot.cpu {
                     Ok(cpu) => {
+                        cpu.state_mut().storage.commit();
                         let src = cpu.state().src();
                         let action = cpu.state().action;
                         tracing::debug!(?src, ?action);
@@ -166,52 +320,13 @@ impl<S: FnMut(Event)> Game<S> {
                             }
                             MotorForward => {
                                 if consume_fuel(cpu, MOVE_FUEL, alive) {
-                                    use TryMoveState::*;
-                                    let state = cpu.state();
-                                    let to = state.at_front();
-                                    let mut mov = match self.map.get(to) {
-                                        Cell::Ground => Valid,
-                                        Cell::Bot(other_id) => {
-                                            // Assume other bot will move successfully
-                                            if let Ok(other) = &others.get(other_id).unwrap().cpu {
-                                                let other = other.state();
-                                                if other.action == MotorForward
-                                                // No passthrough swap
-                                                && other.facing != -state.facing
-                                                {
-                                                    After(other.at_front())
-                                                } else {
-                                                    Cancelled
-                                                }
-                                            } else {
-                                                Cancelled
-                                            }
-                                        }
-                                        Cell::Wall => Cancelled,
-                                    };
-                                    if mov.is_ok()
-                                        && !match self.cache.moves.get_mut(&to) {
-                                            Some(v) => {
-                                                if v.1.is_ok() {
-                                                    let other =
-                                                        others.get(v.0).unwrap().cpu.as_ref();
-                                                    let other = other.unwrap().state().src();
-                                                    self.events.send(BotCollide { src: other, to });
-                                                    v.1 = Cancelled;
-                                                }
-                                                false
-                                            }
-                                            None => true,
-                                        }
-                                    {
-                                        mov = Cancelled;
-                                    }
-                                    if mov.is_ok() {
-                                        self.cache.moves.insert(to, (id, mov));
-                                        //NOTE: Postponed
-                                    } else {
-                                        self.events.send(BotCollide { src, to });
-                                    }
+                                    bot.stats.moves += 1;
+                                    //NOTE: Postponed
+                                    self.cache.moves.push(Move {
+                                        id,
+                                        from: src.at,
+                                        to: cpu.state().at_front(),
+                                    });
                                 }
                             }
                             Wait => {}
@@ -233,6 +348,7 @@ impl<S: FnMut(Event)> Game<S> {
         for id in self.cache.deaths.iter() {
             if let Ok(bot) = self.bots.remove(*id) {
                 let src = bot.src(*id);
+                self.programs[bot.program].stats.merge(&bot.stats);
                 self.map.set(src.at, Cell::Ground);
                 self.events.send(BotDie { src });
             }
@@ -241,147 +357,448 @@ impl<S: FnMut(Event)> Game<S> {
     }
     /// Move bot chains
     /// Consume self.cache.moves
+    ///
+    /// Each move depends on the move of the bot in front of it, if any.
+    /// As only one move can target a given cell, the graph is made of
+    /// disjoint chains and cycles, resolved in linear time.
     #[instrument(level = "trace", skip_all)]
     fn tick_move(&mut self) {
         let ms = &mut self.cache.moves;
-        while let Some((to, id, state)) = next_ok(ms) {
-            let tail = if let Ok(bot) = self.bots.get(id) {
-                bot.at()
-            } else {
-                ms.remove(&to);
-                continue;
-            };
+        // Dead bots do not move
+        ms.retain(|m| self.bots.exists(m.id));
 
-            // Check chain head
-            let mut it = It {
-                to: Some(to),
-                tail,
-                state,
-            };
-            while let TryMoveState::After(_) = it.state {
-                it.next(ms);
+        let states = &mut self.cache.move_states;
+        states.clear();
+        states.resize(ms.len(), MoveState::Pending);
+        let froms = &mut self.cache.move_froms;
+        froms.clear();
+        let heads = &mut self.cache.move_heads;
+        heads.clear();
+        for (i, m) in ms.iter().enumerate() {
+            froms.insert(m.from, i);
+            if let Some(&j) = heads.get(&m.to) {
+                // Head conflict
+                states[i] = MoveState::Conflict;
+                states[j] = MoveState::Conflict;
+            } else {
+                heads.insert(m.to, i);
             }
-            let valid = it.state.is_ok();
-            let head = it.to;
-            it = It {
-                to: Some(to),
-                tail,
-                state,
-            };
+        }
 
-            if valid && head.map_or(true, |at| self.map.get(at).is_empty()) {
-                // Move chain
-                while let Some(to) = it.next(ms) {
-                    let id = ms.remove(&to).unwrap().0;
-                    if let Ok(bot) = self.bots.get_mut(id) {
-                        if let Ok(cpu) = &mut bot.cpu {
-                            let state = cpu.state_mut();
-                            debug_assert!(state.at_front() == to);
-                            let src = state.src();
-                            self.map.set(to, Cell::Bot(id));
-                            state.at = to;
-                            self.events.send(BotMove { src, to });
-                        } else {
-                            panic!("Bot off moved {:?} ???", id)
+        let order = &mut self.cache.move_order;
+        order.clear();
+        let mut path = Vec::new();
+        for i in 0..ms.len() {
+            let mut cur = i;
+            let (ok, cycle) = loop {
+                match states[cur] {
+                    MoveState::Done => break (true, None),
+                    MoveState::Cancelled | MoveState::Conflict => break (false, None),
+                    MoveState::Visiting => {
+                        // Rotating loop without passthrough swap
+                        let start = path.iter().position(|j| *j == cur).unwrap();
+                        break (path.len() - start > 2, Some(start));
+                    }
+                    MoveState::Pending => {
+                        states[cur] = MoveState::Visiting;
+                        path.push(cur);
+                        match froms.get(&ms[cur].to) {
+                            Some(&next) => cur = next,
+                            None => break (self.map.get(ms[cur].to).is_empty(), None),
                         }
                     }
                 }
-                if head.is_some() {
-                    self.map.set(tail, Cell::Ground);
+            };
+            // Head first
+            for (k, j) in path.drain(..).enumerate().rev() {
+                // Following a loop means moving into its cells
+                let ok = ok && cycle.is_none_or(|start| k >= start);
+                states[j] = if ok {
+                    MoveState::Done
+                } else {
+                    MoveState::Cancelled
+                };
+                order.push(j);
+            }
+            if states[i] == MoveState::Conflict {
+                states[i] = MoveState::Cancelled;
+                order.push(i);
+            }
+        }
+
+        for &i in order.iter() {
+            let Move { id, from, to } = ms[i];
+            let bot = self.bots.get_mut(id).unwrap();
+            let src = bot.src(id);
+            if states[i] == MoveState::Done {
+                if let Ok(cpu) = &mut bot.cpu {
+                    let state = cpu.state_mut();
+                    debug_assert!(state.at == from && state.at_front() == to);
+                    state.at = to;
+                    bot.stats.distance += 1;
+                    self.events.send(BotMove { src, to });
+                } else {
+                    panic!("Bot off moved {:?} ???", id)
                 }
             } else {
-                // Cancel chain
-                while let Some(to) = it.next(ms) {
-                    let (id, state) = ms.get_mut(&to).unwrap();
-                    *state = TryMoveState::Cancelled;
-                    let id = *id;
-                    if let Ok(bot) = self.bots.get(id) {
-                        self.events.send(BotCollide {
-                            src: bot.src(id),
-                            to,
+                self.events.send(BotCollide { src, to });
+                bot.stats.collisions += 1;
+                if let Ok(cpu) = &mut bot.cpu {
+                    let res = cpu.on_collide();
+                    cpu.state_mut().take_calls(&mut bot.stats);
+                    self.events.trace(src, cpu.state_mut().take_trace());
+                    self.events.log(src, cpu.store_mut().read_log());
+                    if let Err(err) = res {
+                        self.events.send(BotError {
+                            src,
+                            err: err_wrap("During on_collide", err),
                         });
+                        if let Some(event) = bot.crash(id, &self.crash_policy, self.counter) {
+                            self.events.send(event);
+                        }
                     }
                 }
             }
         }
-        if !ms.is_empty() {
-            tracing::debug!(count = ms.len(), "cancelled moves");
+        let moved = || {
+            order
+                .iter()
+                .filter(|&&i| states[i] == MoveState::Done)
+                .map(|&i| ms[i])
+        };
+        for m in moved() {
+            self.map.set(m.from, Cell::Ground);
+        }
+        for m in moved() {
+            self.map.set(m.to, Cell::Bot(m.id));
         }
         ms.clear();
-
-        #[inline(always)]
-        fn next_ok(ms: &HashMap<Hex, (BotId, TryMoveState)>) -> Option<(Hex, BotId, TryMoveState)> {
-            ms.iter()
-                .find(|(_, (_, state))| state.is_ok())
-                .map(|(at, (id, state))| (*at, *id, *state))
-        }
-        struct It {
-            to: Option<Hex>,
-            tail: Hex,
-            state: TryMoveState,
-        }
-        impl It {
-            fn next(&mut self, ms: &HashMap<Hex, (BotId, TryMoveState)>) -> Option<Hex> {
-                let res = self.to;
-                self.to = if let TryMoveState::After(at) = self.state {
-                    self.state = TryMoveState::Valid;
-                    if let Some((_, next)) = ms.get(&at) {
-                        if at != self.tail {
-                            // No Loop
-                            self.state = *next;
-                        }
-                        Some(at)
-                    } else {
-                        None // Last moved
-                    }
-                } else {
-                    None
-                };
-                res
-            }
+        #[cfg(debug_assertions)]
+        self.check_map();
+    }
+    /// Bots and map are in sync
+    #[cfg(debug_assertions)]
+    fn check_map(&self) {
+        let mut positions = std::collections::HashSet::with_capacity(self.bots.len());
+        for (id, bot) in self.bots.iter() {
+            let at = bot.at();
+            assert!(positions.insert(at), "Two bots at {:?}", at);
+            assert_eq!(self.map.get(at), Cell::Bot(id), "Map out of sync");
         }
     }
 
     #[instrument(level = "trace", name = "command", skip_all)]
     pub fn apply(&mut self, command: Command) {
         match command {
-            Command::Compile(code, cb) => {
-                let res = Program::new(code, &self.vm)
-                    .map(|program| self.programs.push_and_get_key(program))
-                    .map_err(|err| Error::new("Failed to compile", err.root_cause().to_string()));
-                cb.resolve(res)
-            }
+            Command::Compile(code, user, cb) => self.compiler.submit(code, user, cb),
             Command::Map(r, cb) => {
                 let range = r.center.range(r.rad as bulb::hex::I);
                 cb.resolve(CellRange::new(r, &self.map));
             }
-            Command::Spawn(q) => {
-                let i: usize = q.pid.into();
-                let at = q.to;
-                if i >= self.programs.len() {
-                    tracing::warn!("bad {:?}", q.pid);
-                    return; //FIXME: Bad program
+            Command::Spawn(q, cb) => {
+                cb.resolve(self.spawn(q.pid, q.to, q.facing, q.fuel.unwrap_or(SPAWN_FUEL), None))
+            }
+            Command::LoadScenario(scenario, cb) => self.load_scenario(*scenario, cb),
+            Command::LoadMaster(code, cb) => cb.resolve(self.load_master(&code)),
+            Command::SetCrashPolicy(policy) => self.crash_policy = policy,
+            Command::Debug(body, cb) => cb.resolve(self.debug(body)),
+            Command::Metrics(cb) => cb.resolve(self.metrics(true)),
+            Command::Bot(id, cb) => cb.resolve(self.bot(id)),
+            Command::Bots(cb) => cb.resolve(self.bots().collect()),
+            Command::Cell(at, cb) => cb.resolve(self.cell(at)),
+            Command::Program(pid, cb) => cb.resolve(self.program(pid)),
+            Command::TickId(cb) => cb.resolve(self.tick_id()),
+            Command::Despawn(id, cb) => cb.resolve(self.despawn(id)),
+            Command::Teleport(id, to, cb) => cb.resolve(self.teleport(id, to)),
+            Command::SetFacing(id, dir, cb) => cb.resolve(self.set_facing(id, dir)),
+            Command::AddFuel(id, fuel, cb) => cb.resolve(self.add_fuel(id, fuel)),
+            Command::ChangeState(..) | Command::SetTickMs(..) | Command::Step(_) => {
+                unreachable!("Server command")
+            }
+        }
+    }
+
+    fn spawn(
+        &mut self,
+        pid: ProgramId,
+        at: Hex,
+        facing: Direction,
+        fuel: u64,
+        player: Option<PlayerId>,
+    ) -> SpawnRes {
+        let i: usize = pid.into();
+        if i >= self.programs.len() {
+            return Err(SpawnErr::UnknownProgram);
+        }
+        check_spawn_fuel(fuel)?;
+        if !self.map.get(at).is_empty() {
+            return Err(SpawnErr::BusyCell);
+        }
+        self.programs[pid].storage.entry(player).or_default();
+        let bid = self.bots.insert(Bot {
+            program: pid,
+            player,
+            cpu: Err(bot::StateOff { at, facing, fuel }),
+            crashes: Default::default(),
+            stats: Default::default(),
+        });
+        self.map.set(at, Cell::Bot(bid));
+        self.with_tick();
+        self.events.send(Event::BotSpawn {
+            src: BotSrc { bid, at },
+        });
+        Ok(bid)
+    }
+
+    fn debug(&mut self, body: Option<DebugBody>) -> AdminRes {
+        if let Some(body) = &body {
+            self.bots.get(body.bid).map_err(|_| ReplyErr::UnknownBot)?;
+        }
+        self.events.1 = body.map(debug::Debugger::new);
+        Ok(())
+    }
+    fn despawn(&mut self, id: BotId) -> AdminRes {
+        let bot = self.bots.remove(id).map_err(|_| ReplyErr::UnknownBot)?;
+        let src = bot.src(id);
+        self.programs[bot.program].stats.merge(&bot.stats);
+        self.map.set(src.at, Cell::Ground);
+        self.with_tick();
+        self.events.send(BotDie { src });
+        Ok(())
+    }
+    fn teleport(&mut self, id: BotId, to: Hex) -> AdminRes {
+        let bot = self.bots.get_mut(id).map_err(|_| ReplyErr::UnknownBot)?;
+        if !self.map.get(to).is_empty() {
+            return Err(ReplyErr::BusyCell);
+        }
+        let src = bot.src(id);
+        bot.set_at(to);
+        self.map.set(src.at, Cell::Ground);
+        self.map.set(to, Cell::Bot(id));
+        self.with_tick();
+        self.events.send(BotMove { src, to });
+        Ok(())
+    }
+    fn set_facing(&mut self, id: BotId, dir: Direction) -> AdminRes {
+        let bot = self.bots.get_mut(id).map_err(|_| ReplyErr::UnknownBot)?;
+        bot.set_facing(dir);
+        let src = bot.src(id);
+        self.with_tick();
+        self.events.send(BotRotate { src, dir });
+        Ok(())
+    }
+    fn add_fuel(&mut self, id: BotId, fuel: u64) -> AdminRes {
+        let bot = self.bots.get_mut(id).map_err(|_| ReplyErr::UnknownBot)?;
+        bot.add_fuel(fuel).map_err(|err| ReplyErr::Bad {
+            err: format!("{:#}", err).into(),
+        })?;
+        let src = bot.src(id);
+        self.with_tick();
+        self.events.send(BotRefuel { src, fuel });
+        Ok(())
+    }
+
+    /// Replace map and bots with scenario ones once its programs are compiled
+    #[instrument(level = "debug", skip_all, fields(name = %scenario.name))]
+    fn load_scenario(&mut self, scenario: scenario::Scenario, cb: Promise<LoadRes>) {
+        if let Some(prev) = self.loading.take() {
+            prev.cb.resolve(Err(Error::new(
+                "Scenario not loaded",
+                "Replaced by another one".to_string(),
+            )));
+        }
+        let map = match Self::scenario_map(&scenario) {
+            Ok(map) => map,
+            Err(err) => return cb.resolve(Err(err)),
+        };
+        self.loads = self.loads.wrapping_add(1);
+        for (index, code) in scenario.programs.iter().enumerate() {
+            self.compiler
+                .submit_scenario(code.clone(), self.loads, index);
+        }
+        let loading = Loading {
+            id: self.loads,
+            programs: (0..scenario.programs.len()).map(|_| None).collect(),
+            scenario,
+            map,
+            cb,
+        };
+        if loading.programs.is_empty() {
+            self.apply_scenario(loading);
+        } else {
+            self.loading = Some(loading);
+        }
+    }
+    /// Map with scenario bots as placeholders, checking they can spawn
+    fn scenario_map(scenario: &scenario::Scenario) -> Result<GameMap, Error> {
+        let mut map = GameMap::new(scenario.map.seed);
+        for at in scenario.map.walls.iter() {
+            map.set(*at, Cell::Wall);
+        }
+        for at in scenario.map.grounds.iter() {
+            map.set(*at, Cell::Ground);
+        }
+        check_spawn_fuel(SPAWN_FUEL)
+            .map_err(|err| Error::new("Bad scenario", format!("{:?}", err)))?;
+        for (player, bots) in scenario.player_ids().zip(scenario.players.iter()) {
+            for bot in bots.iter() {
+                if bot.program as usize >= scenario.programs.len() {
+                    return Err(Error::new(
+                        "Bad scenario",
+                        format!("{} uses unknown program {}", player, bot.program),
+                    ));
                 }
-                if !self.map.get(at).is_empty() {
-                    tracing::warn!("bad {:?}", at);
-                    return; //FIXME: Bad pos
+                if !map.get(bot.at).is_empty() {
+                    return Err(Error::new(
+                        "Bad scenario",
+                        format!("{} spawns on busy cell {:?}", player, bot.at),
+                    ));
                 }
-                let bid = self.bots.insert(Bot {
-                    program: q.pid,
-                    cpu: Err(bot::StateOff {
-                        at,
-                        facing: bulb::hex::Direction::Up,
-                        fuel: 10_000,
-                    }),
-                });
-                self.map.set(at, Cell::Bot(bid));
-                self.with_tick();
-                self.events.send(Event::BotSpawn {
-                    src: BotSrc { bid, at },
+                map.set(bot.at, Cell::Bot(u64::MAX.into()));
+            }
+        }
+        Ok(map)
+    }
+    fn scenario_compiled(
+        &mut self,
+        load: u32,
+        index: usize,
+        code: Bytes,
+        res: Result<bot::Template>,
+    ) {
+        let Some(loading) = self.loading.as_mut().filter(|l| l.id == load) else {
+            return;
+        };
+        match res {
+            Ok(tpl) => loading.programs[index] = Some(Program::compiled(code, tpl)),
+            Err(err) => {
+                let loading = self.loading.take().unwrap();
+                let err = Error::new("Failed to compile", err.root_cause().to_string());
+                return loading.cb.resolve(Err(err));
+            }
+        }
+        if loading.programs.iter().all(Option::is_some) {
+            let loading = self.loading.take().unwrap();
+            self.apply_scenario(loading);
+        }
+    }
+    fn apply_scenario(&mut self, loading: Loading) {
+        let Loading {
+            scenario,
+            programs,
+            mut map,
+            cb,
+            ..
+        } = loading;
+        self.with_tick();
+        let ids: Vec<BotId> = self.bots.iter().map(|(id, _)| id).collect();
+        for id in ids {
+            let bot = self.bots.remove(id).unwrap();
+            self.programs[bot.program].stats.merge(&bot.stats);
+            self.events.send(BotDie { src: bot.src(id) });
+        }
+        for bots in scenario.players.iter() {
+            for bot in bots.iter() {
+                map.set(bot.at, Cell::Ground);
+            }
+        }
+        self.map = map;
+        let pids: Vec<ProgramId> = programs
+            .into_iter()
+            .map(|program| self.programs.push_and_get_key(program.unwrap()))
+            .collect();
+        for (player, bots) in scenario.player_ids().zip(scenario.players.iter()) {
+            for bot in bots.iter() {
+                let pid = pids[bot.program as usize];
+                if let Err(err) = self.spawn(pid, bot.at, bot.facing, SPAWN_FUEL, Some(player)) {
+                    tracing::error!(?err, "checked scenario spawn failed");
+                }
+            }
+        }
+        self.scenario = Some(objective::Progress::new(&scenario, self.counter));
+        self.over = false;
+        cb.resolve(Ok(()));
+    }
+
+    /// Replace game master program
+    #[instrument(level = "debug", skip_all)]
+    fn load_master(&mut self, code: &[u8]) -> LoadRes {
+        let wasi = wasm::WasiProfile {
+            seed: Some(self.map.seed.into()),
+            tick_ns: Some(WASI_TICK_NS),
+        };
+        let master = master::Master::boot(code, &wasi)
+            .map_err(|err| Error::new("Failed to start game master", format!("{:#}", err)))?;
+        self.master = Some(master);
+        Ok(())
+    }
+
+    pub fn bot(&self, id: BotId) -> Option<BotInfo> {
+        self.bots.get(id).ok().map(|bot| bot.info(id))
+    }
+    pub fn bots(&self) -> impl Iterator<Item = BotInfo> + '_ {
+        self.bots.iter().map(|(id, bot)| bot.info(id))
+    }
+    #[inline]
+    pub fn cell(&self, at: Hex) -> Cell {
+        self.map.get(at)
+    }
+    pub fn program(&self, pid: ProgramId) -> Option<ProgramInfo> {
+        let program = self.programs.get(pid)?;
+        Some(ProgramInfo {
+            pid,
+            bots: self
+                .bots
+                .iter()
+                .filter(|(_, bot)| bot.program == pid)
+                .count() as u32,
+            size: program.code.len() as u32,
+            storage: (program.storage.values())
+                .map(|bucket| bucket.read().unwrap().size() as u32)
+                .sum(),
+        })
+    }
+    /// Current tick, or next one if not in tick
+    #[inline]
+    pub fn tick_id(&self) -> TickId {
+        self.counter.into()
+    }
+
+    /// Statistics of programs, including dead bots, and of alive bots if `with_bots`
+    fn metrics(&self, with_bots: bool) -> Metrics {
+        let mut programs: Vec<_> = (self.programs.iter_enumerated())
+            .map(|(pid, program)| ProgramMetrics {
+                pid,
+                bots: 0,
+                stats: program.stats.clone(),
+            })
+            .collect();
+        let mut bots = Vec::new();
+        for (bid, bot) in self.bots.iter() {
+            let program = &mut programs[usize::from(bot.program)];
+            program.bots += 1;
+            program.stats.merge(&bot.stats);
+            if with_bots {
+                bots.push(BotMetrics {
+                    bid,
+                    pid: bot.program,
+                    stats: bot.stats.clone(),
                 });
             }
-            Command::ChangeState(_) => unreachable!("Server command"),
         }
+        Metrics { programs, bots }
+    }
+
+    /// Debugged bot hit a breakpoint since last call
+    #[inline]
+    pub fn take_break(&mut self) -> bool {
+        std::mem::take(&mut self.hit_break)
+    }
+
+    /// Game ended by scenario or game master
+    #[inline]
+    pub fn is_over(&self) -> bool {
+        self.over
     }
 
     #[inline]
@@ -405,11 +822,14 @@ type Bots = gen::Array<BotId, Bot>;
 struct GameMap {
     pub grid: BTreeMap<Hex, Cell>,
     pub gen: MapGenerator,
+    /// World seed
+    pub seed: u32,
 }
 impl GameMap {
     fn new(seed: u32) -> Self {
         Self {
             grid: BTreeMap::new(),
+            seed,
             gen: MapGenerator::new(seed),
         }
     }
@@ -450,72 +870,133 @@ impl MapGenerator {
 }
 
 struct GameCache {
-    moves: HashMap<Hex, (BotId, TryMoveState)>,
+    moves: Vec<Move>,
+    move_states: Vec<MoveState>,
+    move_froms: HashMap<Hex, usize>,
+    move_heads: HashMap<Hex, usize>,
+    move_order: Vec<usize>,
     deaths: Vec<BotId>,
 }
 impl GameCache {
     fn new() -> Self {
         Self {
-            moves: HashMap::new(),
+            moves: Vec::new(),
+            move_states: Vec::new(),
+            move_froms: HashMap::new(),
+            move_heads: HashMap::new(),
+            move_order: Vec::new(),
             deaths: Vec::new(),
         }
     }
 }
+#[derive(Clone, Copy)]
+struct Move {
+    id: BotId,
+    from: Hex,
+    to: Hex,
+}
 #[derive(Clone, Copy, PartialEq)]
-enum TryMoveState {
-    Valid,
-    After(Hex),
+enum MoveState {
+    Pending,
+    Visiting,
+    Done,
     Cancelled,
-}
-impl TryMoveState {
-    #[inline]
-    fn is_ok(&self) -> bool {
-        *self != Self::Cancelled
-    }
+    /// Cancelled but not yet ordered
+    Conflict,
 }
 
-struct EventSender<S>(S);
+/// Sink of events, watched by debugger if any
+struct EventSender<S>(S, Option<debug::Debugger>);
 impl<S: FnMut(Event)> EventSender<S> {
     fn send(&mut self, event: Event) {
         tracing::trace!(?event);
+        if let Some(debug) = &mut self.1 {
+            debug.watch(&event);
+        }
         self.0(event);
     }
     #[inline]
-    fn log(&mut self, src: BotSrc, log: String) {
-        if !log.is_empty() {
+    fn trace(&mut self, src: BotSrc, (calls, skipped): (Vec<HostCall>, u32)) {
+        for call in calls {
+            self.send(BotTrace { src, call })
+        }
+        if skipped > 0 {
+            self.send(BotLog {
+                src,
+                log: LogRecord {
+                    level: LogLevel::Warn,
+                    msg: format!("{} more host calls not traced", skipped).into(),
+                    fields: Vec::new(),
+                },
+            })
+        }
+    }
+    #[inline]
+    fn log(&mut self, src: BotSrc, log: Vec<sys::log::Record>) {
+        for record in log {
             self.send(BotLog {
                 src,
-                msg: log.into(),
+                log: log_record(record),
             })
         }
     }
 }
 
+/// Shared by bots during parallel tick
+#[derive(Clone, Copy)]
+struct TickCtx<'a> {
+    programs: &'a Programs,
+    map: &'a GameMap,
+    tick: u32,
+    policy: &'a CrashPolicy,
+    /// Debugged bot
+    traced: Option<BotId>,
+}
+
+fn check_spawn_fuel(fuel: u64) -> Result<(), SpawnErr> {
+    if fuel > MAX_SPAWN_FUEL {
+        return Err(SpawnErr::TooMuchFuel {
+            max: MAX_SPAWN_FUEL,
+        });
+    }
+    if fuel < MIN_BOOT_FUEL {
+        return Err(SpawnErr::NotEnoughFuel { min: MIN_BOOT_FUEL });
+    }
+    Ok(())
+}
+
+/// Scenario waiting for its programs to compile
+struct Loading {
+    id: u32,
+    scenario: scenario::Scenario,
+    /// Compiled ones by index
+    programs: Vec<Option<Program>>,
+    /// With placeholder bots
+    map: GameMap,
+    cb: Promise<LoadRes>,
+}
+
 type Programs = TiVec<ProgramId, Program>;
 struct Program {
-    inner: Option<bot::Template>,
+    inner: bot::Template,
     code: Bytes,
+    /// Shared by bots of each owner, survives their death
+    storage: BTreeMap<Option<PlayerId>, storage::Shared>,
+    /// Of dead bots
+    stats: Stats,
 }
 impl Program {
-    fn new(code: Bytes, vm: &VM) -> Result<Self> {
-        let mut s = Self { inner: None, code };
-        s.compile(vm)?;
-        Ok(s)
-    }
-
-    fn compiled(&mut self, vm: &VM) -> Result<&mut bot::Template> {
-        if self.inner.is_none() {
-            self.compile(vm)?;
+    fn compiled(code: Bytes, tpl: bot::Template) -> Self {
+        Self {
+            inner: tpl,
+            code,
+            storage: Default::default(),
+            stats: Default::default(),
         }
-        Ok(self.inner.as_mut().unwrap())
     }
-    #[cold]
+
     #[inline]
-    #[instrument(level = "trace", skip_all)]
-    fn compile(&mut self, vm: &VM) -> Result<()> {
-        debug_assert!(self.inner.is_none());
-        let tpl = bot::Template::new(vm, &self.code)?;
-        self.inner = Some(tpl);
-        Ok(())
+    fn template(&self) -> &bot::Template {
+        &self.inner
     }
 }
@@ -0,0 +1,142 @@
+//! Trusted game master program with privileged api
+
+use super::api::with_mem;
+use super::wasm::{
+    self,
+    spec::{Instance as _, Linker as _, Store as _},
+};
+use bulb::{
+    dto::{Cell, PlayerId, ProgramId},
+    hex::{Direction, Hex},
+};
+use sys::{err_str, Result};
+use tracing::instrument;
+
+/// Fuel available for each tick
+pub const MASTER_FUEL: u64 = 1_000_000;
+
+pub type VM = wasm::Linker<Store>;
+pub fn new_vm() -> Result<VM> {
+    let mut vm = VM::new(&wasm::Engine::new());
+    vm.add_wasi()
+        .add_export(wasm::spec::MAY_EXPORT_START.clone())
+        .add_export(wasm::spec::LinkExport {
+            name: "tick",
+            required: true,
+            value: wasm::spec::ExportType::UnitFunc,
+        });
+
+    vm.add_func("world", "tick", |gm: Caller| gm.state().tick)?;
+
+    vm.add_func(
+        "world",
+        "spawn",
+        |gm: Caller, pid: u32, q: i32, r: i32, facing: u32| {
+            gm.state_mut().orders.push(Order::Spawn {
+                pid: pid.into(),
+                at: Hex::new(q, r),
+                facing: Direction::all()[facing as usize % 6],
+            })
+        },
+    )?
+    .add_func("world", "set", |gm: Caller, q: i32, r: i32, cell: u32| {
+        let cell = match cell {
+            0 => Cell::Ground,
+            1 => Cell::Wall,
+            _ => return Err(err_str!("Invalid cell {}", cell)),
+        };
+        gm.state_mut().orders.push(Order::Set {
+            at: Hex::new(q, r),
+            cell,
+        });
+        Ok(())
+    })?;
+
+    vm.add_func("game", "announce", |gm: Caller, ptr: u32, len: u32| {
+        let (buf, ctx) = with_mem(gm, ptr, len)?;
+        let msg = String::from_utf8_lossy(buf).into_owned();
+        ctx.state_mut().orders.push(Order::Announce(msg));
+        Ok(())
+    })?
+    .add_func("game", "end", |gm: Caller, winner: i32| {
+        let winner = u32::try_from(winner).ok().map(PlayerId::from);
+        gm.state_mut().orders.push(Order::End { winner })
+    })?;
+
+    Ok(vm)
+}
+
+type Caller<'a> = &'a mut dyn wasm::spec::Caller<Store>;
+pub type Store = wasm::WasiStore<State>;
+
+#[derive(Default)]
+pub struct State {
+    /// Current tick id
+    pub tick: u32,
+    /// Requests to apply after call
+    pub orders: Vec<Order>,
+}
+
+#[derive(Debug)]
+pub enum Order {
+    Spawn {
+        pid: ProgramId,
+        at: Hex,
+        facing: Direction,
+    },
+    Set {
+        at: Hex,
+        cell: Cell,
+    },
+    Announce(String),
+    End {
+        winner: Option<PlayerId>,
+    },
+}
+
+pub struct Master {
+    process: wasm::Instance<Store>,
+    tick: wasm::Func<(), ()>,
+}
+impl Master {
+    #[cold]
+    #[instrument(level = "debug", skip_all)]
+    pub fn boot(code: &[u8], wasi: &wasm::WasiProfile) -> Result<Self> {
+        let vm = new_vm()?;
+        let tpl = vm.link(code)?;
+        let mut process = wasm::Instance::new(&tpl, State::default(), MASTER_FUEL)?;
+        process.store_mut().set_profile(wasi);
+        let res = process.start();
+        log(process.store_mut());
+        res?;
+        let tick = process.get_func::<(), ()>("tick")?;
+        Ok(Self { process, tick })
+    }
+
+    /// Call tick and collect requested orders
+    #[instrument(level = "debug", name = "master", skip_all)]
+    pub fn tick(&mut self, tick: u32) -> (Vec<Order>, Result<()>) {
+        let fuel = self.process.fuel();
+        if fuel < MASTER_FUEL {
+            _ = self.process.add_fuel(MASTER_FUEL - fuel);
+        }
+        self.process.state_mut().tick = tick;
+        self.process.store_mut().set_tick(tick.into());
+        let res = self.process.call(&self.tick, ());
+        log(self.process.store_mut());
+        (std::mem::take(&mut self.process.state_mut().orders), res)
+    }
+}
+
+fn log(store: &mut Store) {
+    use sys::log::Level;
+    for record in store.read_log() {
+        let (msg, fields) = (record.msg, record.fields);
+        match record.level {
+            Level::Debug => tracing::debug!(?fields, "{}", msg),
+            Level::Info => tracing::info!(?fields, "{}", msg),
+            Level::Warn => tracing::warn!(?fields, "{}", msg),
+            Level::Error => tracing::error!(?fields, "{}", msg),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use super::{Bots, EventSender, GameMap};
+use bulb::dto::{Cell, CellMap, Event, PlayerId};
+use bulb::scenario::{Objective, Scenario};
+use tracing::instrument;
+
+/// Objectives progress of loaded scenario
+pub struct Progress {
+    objectives: Vec<Objective>,
+    /// Tick id limit
+    end: Option<u32>,
+    /// Completed objectives by player
+    done: Vec<Vec<bool>>,
+    over: bool,
+}
+impl Progress {
+    pub fn new(scenario: &Scenario, counter: u32) -> Self {
+        Self {
+            objectives: scenario.objectives.clone(),
+            end: scenario
+                .time_limit
+                .map(|limit| counter.saturating_add(limit)),
+            done: vec![vec![false; scenario.objectives.len()]; scenario.players.len()],
+            over: false,
+        }
+    }
+
+    #[inline]
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    /// Evaluate objectives at the end of a tick
+    #[instrument(level = "trace", name = "scenario", skip_all)]
+    pub fn update<S: FnMut(Event)>(
+        &mut self,
+        counter: u32,
+        bots: &Bots,
+        map: &GameMap,
+        events: &mut EventSender<S>,
+    ) {
+        if self.over {
+            return;
+        }
+
+        let mut alive = vec![0usize; self.done.len()];
+        for (_, bot) in bots.iter() {
+            if let Some(n) = bot
+                .player
+                .and_then(|p| alive.get_mut(u32::from(p) as usize))
+            {
+                *n += 1;
+            }
+        }
+
+        let players = self.done.len();
+        let mut winner = None;
+        for (p, done) in self.done.iter_mut().enumerate() {
+            let player = PlayerId::from(p as u32);
+            for (oid, objective) in self.objectives.iter().enumerate() {
+                if done[oid] {
+                    continue;
+                }
+                let ok = match objective {
+                    Objective::ReachHex { at } => match map.get(*at) {
+                        Cell::Bot(id) => bots.get(id).is_ok_and(|bot| bot.player == Some(player)),
+                        _ => false,
+                    },
+                    Objective::LastPlayerStanding => {
+                        players >= 2
+                            && alive[p] > 0
+                            && alive.iter().enumerate().all(|(o, n)| o == p || *n == 0)
+                    }
+                };
+                if ok {
+                    done[oid] = true;
+                    events.send(Event::ScenarioProgress {
+                        player,
+                        oid: oid as u32,
+                    });
+                }
+            }
+            if winner.is_none() && !done.is_empty() && done.iter().all(|d| *d) {
+                winner = Some(player);
+            }
+        }
+
+        if winner.is_some() || self.end.is_some_and(|end| counter + 1 >= end) {
+            tracing::debug!(?winner, "game over");
+            self.over = true;
+            events.send(Event::GameOver { winner });
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Key-value storage of a program shared by its bots of a same owner
+//!
+//! Writes are buffered by each bot and committed in bot order after tick,
+//! so parallel ticks stay deterministic.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+pub const MAX_KEY_LEN: usize = 64;
+pub const MAX_VALUE_LEN: usize = 1024;
+/// Sum of keys and values length by program and owner
+pub const MAX_BUCKET_SIZE: usize = 64 * 1024;
+
+type Key = Box<[u8]>;
+type Value = Box<[u8]>;
+
+#[derive(Default)]
+pub struct Bucket {
+    values: BTreeMap<Key, Value>,
+    size: usize,
+}
+impl Bucket {
+    #[inline]
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.values.get(key).map(AsRef::as_ref)
+    }
+    /// Total length of keys and values
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn set(&mut self, key: Key, value: Value) -> bool {
+        let old = self.get(&key).map_or(0, |v| v.len() + key.len());
+        let size = self.size - old + key.len() + value.len();
+        if size > MAX_BUCKET_SIZE {
+            return false;
+        }
+        self.size = size;
+        self.values.insert(key, value);
+        true
+    }
+    fn delete(&mut self, key: &[u8]) -> bool {
+        match self.values.remove_entry(key) {
+            Some((key, value)) => {
+                self.size -= key.len() + value.len();
+                true
+            }
+            None => false,
+        }
+    }
+}
+pub type Shared = Arc<RwLock<Bucket>>;
+
+/// Bot view of its owner bucket
+#[derive(Default)]
+pub struct Storage {
+    bucket: Shared,
+    /// Pending writes, `None` for delete
+    pending: BTreeMap<Key, Option<Value>>,
+}
+impl Storage {
+    pub fn new(bucket: Shared) -> Self {
+        Self {
+            bucket,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Read including own pending writes
+    pub fn get<R>(&self, key: &[u8], f: impl FnOnce(Option<&[u8]>) -> R) -> R {
+        if let Some(pending) = self.pending.get(key) {
+            return f(pending.as_deref());
+        }
+        f(self.bucket.read().unwrap().get(key))
+    }
+    /// Returns `false` if key or value is too large or if bucket would be full
+    pub fn set(&mut self, key: &[u8], value: &[u8]) -> bool {
+        if key.len() > MAX_KEY_LEN || value.len() > MAX_VALUE_LEN {
+            return false;
+        }
+        let bucket = self.bucket.read().unwrap();
+        // Size once committed, with pending writes replacing their entries
+        let mut size = bucket.size();
+        let writes = (self.pending.iter())
+            .filter(|(k, _)| k.as_ref() != key)
+            .filter_map(|(k, v)| v.as_deref().map(|v| (k.as_ref(), v)))
+            .chain(std::iter::once((key, value)));
+        for (k, v) in writes {
+            size -= bucket.get(k).map_or(0, |old| k.len() + old.len());
+            size += k.len() + v.len();
+        }
+        drop(bucket);
+        if size > MAX_BUCKET_SIZE {
+            return false;
+        }
+        self.pending.insert(key.into(), Some(value.into()));
+        true
+    }
+    /// Returns `false` if key was not present
+    pub fn delete(&mut self, key: &[u8]) -> bool {
+        let exists = self.get(key, |v| v.is_some());
+        if exists {
+            self.pending.insert(key.into(), None);
+        }
+        exists
+    }
+
+    /// Apply pending writes to owner bucket, ignoring those overflowing it
+    pub fn commit(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let mut bucket = self.bucket.write().unwrap();
+        for (key, value) in std::mem::take(&mut self.pending) {
+            match value {
+                Some(value) => _ = bucket.set(key, value),
+                None => _ = bucket.delete(&key),
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Game driven by hand on the mock backend
+#![allow(dead_code)]
+
+use bulb::hex::{Direction, Hex, I};
+use scalliony_engine::scenario::Scenario;
+use scalliony_engine::*;
+use std::sync::mpsc;
+
+/// Calls `motor.forward` every tick
+pub const FORWARD: &str = "tick motor@1.forward";
+/// Does nothing
+pub const WAIT: &str = "tick";
+
+pub struct World {
+    pub game: Game<Box<dyn FnMut(Event)>>,
+    events: mpsc::Receiver<Event>,
+}
+impl World {
+    /// Ground hexagon of radius `rad` surrounded by walls running `programs`
+    pub fn new(rad: I, programs: &[&str]) -> Self {
+        Self::load(arena(rad, programs))
+    }
+    pub fn load(scenario: Scenario) -> Self {
+        let (tx, events) = mpsc::channel();
+        let mut world = Self {
+            game: Game::new(Box::new(move |e| _ = tx.send(e))),
+            events,
+        };
+        world.load_scenario(scenario).unwrap();
+        world.events();
+        world
+    }
+
+    /// Apply a command and wait for its promise
+    pub fn ask<V: Send + 'static>(&mut self, cmd: impl FnOnce(Promise<V>) -> Command) -> V {
+        let (tx, rx) = mpsc::channel();
+        self.game.apply(cmd(Promise::new(move |v| _ = tx.send(v))));
+        rx.try_recv().expect("promise resolved")
+    }
+    pub fn spawn(&mut self, pid: u32, to: Hex, facing: Direction, fuel: Option<u64>) -> SpawnRes {
+        self.ask(|p| {
+            Command::Spawn(
+                SpawnBody {
+                    pid: pid.into(),
+                    to,
+                    facing,
+                    fuel,
+                },
+                p,
+            )
+        })
+    }
+    /// Compile on worker threads and wait for the result
+    /// Apply a command and wait for its promise while programs compile
+    pub fn wait<V: Send + 'static>(&mut self, cmd: impl FnOnce(Promise<V>) -> Command) -> V {
+        let (tx, rx) = mpsc::channel();
+        self.game.apply(cmd(Promise::new(move |v| _ = tx.send(v))));
+        loop {
+            self.game.poll_compiled();
+            if let Ok(res) = rx.try_recv() {
+                return res;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+    pub fn compile(&mut self, code: &str) -> CompileRes {
+        let code = Bytes::from(code.as_bytes().to_vec());
+        self.wait(|p| Command::Compile(code, "tester".into(), p))
+    }
+    pub fn load_scenario(&mut self, scenario: Scenario) -> LoadRes {
+        self.wait(|p| Command::LoadScenario(Box::new(scenario), p))
+    }
+    /// Events since last call
+    pub fn events(&mut self) -> Vec<Event> {
+        self.events.try_iter().collect()
+    }
+    pub fn tick(&mut self) -> Vec<Event> {
+        self.game.tick();
+        self.events()
+    }
+
+    /// Every bot is alone on its cell and every bot cell of `area` has a bot
+    pub fn check_map(&self, area: impl Iterator<Item = Hex>) {
+        let mut bots = 0;
+        for bot in self.game.bots() {
+            assert_eq!(self.game.cell(bot.at), Cell::Bot(bot.bid), "{:?}", bot);
+            bots += 1;
+        }
+        let cells = area
+            .filter(|at| matches!(self.game.cell(*at), Cell::Bot(_)))
+            .count();
+        assert_eq!(cells, bots, "bot cells without bot");
+    }
+}
+
+/// Ground hexagon of radius `rad` surrounded by walls
+pub fn arena(rad: I, programs: &[&str]) -> Scenario {
+    let mut scenario = Scenario::default();
+    scenario.map.grounds = Hex::default().range(rad).collect();
+    scenario.map.walls = Hex::default()
+        .range(rad + 1)
+        .filter(|at| at.length() > rad)
+        .collect();
+    scenario.programs = programs
+        .iter()
+        .map(|code| Bytes::from(code.as_bytes().to_vec()))
+        .collect();
+    scenario
+}
@@ -0,0 +1,266 @@
+#![cfg(feature = "mock")]
+//! Engine behavior with scripted programs
+
+mod common;
+use bulb::hex::{Direction, Hex};
+use common::*;
+use scalliony_engine::*;
+use std::sync::mpsc;
+use std::time::Duration;
+
+const TRAP: &str = "tick trap boom";
+
+fn crashing(policy: CrashPolicy) -> (World, BotId) {
+    let mut world = World::new(1, &[TRAP]);
+    world.game.apply(Command::SetCrashPolicy(policy));
+    let bid = world.spawn(0, Hex::default(), Direction::Up, None).unwrap();
+    world.events();
+    (world, bid)
+}
+fn count(events: &[Event], f: impl Fn(&Event) -> bool) -> usize {
+    events.iter().filter(|e| f(e)).count()
+}
+
+#[test]
+fn crash_halt_stops_bot() {
+    let (mut world, bid) = crashing(CrashPolicy::Halt { errors: 2 });
+    let events = world.tick();
+    assert_eq!(count(&events, |e| matches!(e, BotError { .. })), 1);
+    assert_eq!(count(&events, |e| matches!(e, BotHalt { .. })), 0);
+    let events = world.tick();
+    assert_eq!(count(&events, |e| matches!(e, BotHalt { .. })), 1);
+    assert!(!world.game.bot(bid).unwrap().running);
+    for _ in 0..3 {
+        let events = world.tick();
+        assert_eq!(count(&events, |e| matches!(e, BotError { .. })), 0);
+    }
+}
+
+#[test]
+fn crash_kill_removes_bot() {
+    let (mut world, bid) = crashing(CrashPolicy::Kill { errors: 1 });
+    let events = world.tick();
+    assert_eq!(count(&events, |e| matches!(e, BotKill { .. })), 1);
+    assert_eq!(count(&events, |e| matches!(e, BotDie { .. })), 1);
+    assert!(world.game.bot(bid).is_none());
+    assert_eq!(world.game.cell(Hex::default()), Cell::Ground);
+}
+
+#[test]
+fn crash_reboot_backs_off() {
+    let (mut world, _) = crashing(CrashPolicy::Reboot {
+        errors: 1,
+        backoff: 2,
+        max_backoff: 3,
+    });
+    let delays: Vec<u32> = (0..12)
+        .flat_map(|_| world.tick())
+        .filter_map(|e| match e {
+            BotReboot { delay, .. } => Some(delay),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(delays[..3], [2, 3, 3]);
+}
+
+#[test]
+fn trace_is_capped() {
+    let code = "tick sys@1.random\n".repeat(300);
+    let mut world = World::new(1, &[&code]);
+    let bid = world.spawn(0, Hex::default(), Direction::Up, None).unwrap();
+    world.game.apply(Command::Debug(
+        Some(DebugBody {
+            bid,
+            breakpoints: Vec::new(),
+        }),
+        Promise::new(|_| {}),
+    ));
+    let events = world.tick();
+    assert_eq!(count(&events, |e| matches!(e, BotTrace { .. })), 256);
+    let skipped = events.iter().any(|e| match e {
+        BotLog { log, .. } => log.msg.as_ref() == "44 more host calls not traced",
+        _ => false,
+    });
+    assert!(skipped);
+}
+
+#[test]
+fn breakpoint_pauses_game_state() {
+    let (tx, rx) = mpsc::channel();
+    let mut state = GameState::new(move || rx.try_recv().ok(), |_| {}, true, 1);
+    let scenario = arena(1, &[FORWARD]);
+    let (loaded_tx, loaded) = mpsc::channel();
+    tx.send(Command::LoadScenario(
+        Box::new(scenario),
+        Promise::new(move |res: LoadRes| _ = loaded_tx.send(res)),
+    ))
+    .unwrap();
+    // Programs compile while paused
+    while loaded.try_recv().is_err() {
+        assert_eq!(state.update(), State::Paused);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    tx.send(Command::Spawn(
+        SpawnBody {
+            pid: 0u32.into(),
+            to: Hex::default(),
+            facing: Direction::Up,
+            fuel: None,
+        },
+        Promise::new(|_| {}),
+    ))
+    .unwrap();
+    tx.send(Command::Debug(
+        Some(DebugBody {
+            bid: 0u64.into(),
+            breakpoints: vec![Breakpoint::Collide],
+        }),
+        Promise::new(|_| {}),
+    ))
+    .unwrap();
+    tx.send(Command::ChangeState(State::Running, Promise::new(|_| {})))
+        .unwrap();
+    // Moves once then hits the wall
+    assert_eq!(state.update(), State::Running);
+    assert_eq!(state.update(), State::Paused);
+    assert_eq!(state.update(), State::Paused);
+}
+
+#[test]
+fn compiles_while_paused() {
+    let (tx, rx) = mpsc::channel();
+    let mut state = GameState::new(move || rx.try_recv().ok(), |_| {}, true, 1);
+    let (res_tx, res_rx) = mpsc::channel();
+    tx.send(Command::Compile(
+        Bytes::from_static(WAIT.as_bytes()),
+        "me".into(),
+        Promise::new(move |r| _ = res_tx.send(r)),
+    ))
+    .unwrap();
+    for _ in 0..100 {
+        assert_eq!(state.update(), State::Paused);
+        if let Ok(res) = res_rx.try_recv() {
+            assert!(res.is_ok());
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("not compiled while paused");
+}
+
+#[test]
+fn run_applies_commands_between_ticks() {
+    let (tx, rx) = mpsc::channel();
+    let mut state = GameState::new(|| None, |_| {}, false, 0);
+    assert_eq!(state.tick_ms(), 1);
+    tx.send(Command::SetTickMs(u64::MAX, Promise::new(|_| {})))
+        .unwrap();
+    tx.send(Command::ChangeState(State::Stopped, Promise::new(|_| {})))
+        .unwrap();
+    let start = std::time::Instant::now();
+    state.run(|timeout| rx.recv_timeout(timeout));
+    assert_eq!(state.tick_ms(), MAX_TICK_DURATION_MS);
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn same_state_does_not_wake_run() {
+    let mut state = GameState::new(|| None, |_| {}, true, 1);
+    let noop = || Promise::new(|_| {});
+    assert!(!state.apply(Command::ChangeState(State::Paused, noop())));
+    assert!(state.apply(Command::ChangeState(State::Running, noop())));
+    assert!(!state.apply(Command::ChangeState(State::Running, noop())));
+}
+
+#[test]
+fn host_calls_are_counted() {
+    const CALLS: &str = "data 0 hi
+on_boot io@1.log 0 2
+tick sys@1.random
+tick motor@1.left
+tick sys@1.random";
+    let mut world = World::new(1, &[CALLS]);
+    let kept = world.spawn(0, Hex::default(), Direction::Up, None).unwrap();
+    let gone = (world.spawn(0, Hex::from(Direction::Up), Direction::Up, None)).unwrap();
+    for _ in 0..3 {
+        world.tick();
+    }
+    world.ask(|p| Command::Despawn(gone, p)).unwrap();
+    world.tick();
+
+    let metrics = world.ask(Command::Metrics);
+    let calls = |stats: &Stats| -> Vec<(String, u64)> {
+        (stats.calls.iter())
+            .map(|(name, n)| (name.to_string(), *n))
+            .collect()
+    };
+    let expected = |boots, ticks| {
+        vec![
+            ("io.log".to_string(), boots),
+            ("motor.left".to_string(), ticks),
+            ("sys.random".to_string(), ticks * 2),
+        ]
+    };
+    assert_eq!(metrics.bots.len(), 1);
+    assert_eq!(metrics.bots[0].bid, kept);
+    assert_eq!(calls(&metrics.bots[0].stats), expected(1, 4));
+    // Including despawned bot
+    assert_eq!(calls(&metrics.programs[0].stats), expected(2, 7));
+}
+
+/// Promise of admin command and receiver of its result
+fn admin() -> (Promise<AdminRes>, mpsc::Receiver<AdminRes>) {
+    let (tx, rx) = mpsc::channel();
+    (Promise::new(move |res| _ = tx.send(res)), rx)
+}
+
+#[test]
+fn admin_commands_reply() {
+    let (tx, events) = mpsc::channel();
+    let mut state = GameState::new(|| None, move |e| _ = tx.send(e), true, 1);
+    let ticks = |events: &mpsc::Receiver<Event>| {
+        (events.try_iter())
+            .filter(|e| matches!(e, Event::TickEnd))
+            .count()
+    };
+
+    let (p, rx) = admin();
+    assert!(!state.apply(Command::SetTickMs(5, p)));
+    assert_eq!(state.tick_ms(), 5);
+    assert!(matches!(rx.try_recv(), Ok(Ok(()))));
+    assert!(matches!(
+        events.try_recv(),
+        Ok(Event::TickRateChange { ms: 5 })
+    ));
+
+    // Steps tick one by one while paused
+    let (p1, rx1) = admin();
+    let (p2, rx2) = admin();
+    assert!(state.apply(Command::Step(p1)));
+    assert!(state.apply(Command::Step(p2)));
+    assert_eq!(state.update(), State::Paused);
+    assert!(matches!(rx1.try_recv(), Ok(Ok(()))));
+    assert!(rx2.try_recv().is_err());
+    assert_eq!(ticks(&events), 1);
+    assert_eq!(state.update(), State::Paused);
+    assert!(matches!(rx2.try_recv(), Ok(Ok(()))));
+    assert_eq!(state.update(), State::Paused);
+    assert_eq!(ticks(&events), 1);
+
+    // Pending steps are dropped when resumed
+    let (step, step_rx) = admin();
+    assert!(state.apply(Command::Step(step)));
+    let (p, rx) = admin();
+    assert!(state.apply(Command::ChangeState(State::Running, p)));
+    assert!(matches!(rx.try_recv(), Ok(Ok(()))));
+    assert!(matches!(step_rx.try_recv(), Ok(Err(ReplyErr::NotPaused))));
+    assert!(matches!(
+        events.try_recv(),
+        Ok(Event::StateChange(State::Running))
+    ));
+    let (step, step_rx) = admin();
+    assert!(!state.apply(Command::Step(step)));
+    assert!(matches!(step_rx.try_recv(), Ok(Err(ReplyErr::NotPaused))));
+    assert_eq!(state.update(), State::Running);
+    assert_eq!(ticks(&events), 1);
+}
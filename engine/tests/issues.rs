@@ -0,0 +1,66 @@
+#![cfg(not(feature = "mock"))]
+//! Link issues of wasm programs reported to players
+
+mod common;
+use common::*;
+use scalliony_engine::*;
+
+/// Issues of a rejected module
+fn issues(world: &mut World, wat: &str) -> Vec<LinkIssue> {
+    let err = world.compile(wat).unwrap_err();
+    assert_eq!(err.err.ctx.as_ref(), "Failed to compile");
+    err.issues
+}
+
+#[test]
+fn every_link_issue_is_reported() {
+    let mut world = World::new(1, &[]);
+    assert!(world.compile(r#"(module (func (export "tick")))"#).is_ok());
+
+    let found = issues(
+        &mut world,
+        r#"(module
+          (import "motor@1" "fly" (func))
+          (func (export "tick")))"#,
+    );
+    assert!(
+        matches!(&found[..], [LinkIssue::UnknownImport { module, name }]
+        if module.as_ref() == "motor@1" && name.as_ref() == "fly")
+    );
+
+    let found = issues(
+        &mut world,
+        r#"(module
+          (import "motor@1" "forward" (func (param i64)))
+          (func (export "tick")))"#,
+    );
+    assert!(
+        matches!(&found[..], [LinkIssue::ImportSignature { name, found: Some(_), .. }]
+        if name.as_ref() == "forward")
+    );
+
+    let found = issues(
+        &mut world,
+        r#"(module
+          (import "motor" "forward" (func))
+          (import "motor@1" "left" (func))
+          (func (export "tick")))"#,
+    );
+    assert!(matches!(&found[..], [LinkIssue::MixedVersions { versions }]
+        if versions == &[0, 1]));
+
+    let found = issues(&mut world, r#"(module (func (export "on_boot")))"#);
+    assert!(matches!(&found[..], [LinkIssue::MissingExport { name }]
+        if name.as_ref() == "tick"));
+
+    let found = issues(
+        &mut world,
+        r#"(module
+          (func (export "tick"))
+          (func (export "on_collide") (param i32)))"#,
+    );
+    assert!(
+        matches!(&found[..], [LinkIssue::ExportSignature { name, found: Some(_), .. }]
+        if name.as_ref() == "on_collide")
+    );
+}
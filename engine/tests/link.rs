@@ -0,0 +1,18 @@
+#![cfg(feature = "mock")]
+//! Program validation against host functions
+
+mod common;
+use common::*;
+use scalliony_engine::*;
+
+#[test]
+fn wasi_is_not_versioned() {
+    let mut world = World::new(1, &[]);
+    let code = "tick motor@1.forward\ntick wasi_snapshot_preview1.sched_yield";
+    assert!(world.compile(code).is_ok());
+    let funcs = host_funcs(ABI).unwrap();
+    assert!(!funcs.is_empty());
+    assert!(funcs
+        .iter()
+        .all(|(module, _, _)| !module.starts_with("wasi")));
+}
@@ -0,0 +1,24 @@
+#![cfg(feature = "mock")]
+//! Bot logs forwarding
+
+mod common;
+use bulb::hex::{Direction, Hex};
+use common::*;
+use scalliony_engine::*;
+
+#[test]
+fn empty_logs_are_bounded() {
+    let code = "tick io@1.log 0 0\n".repeat(1000);
+    let mut world = World::new(1, &[&code]);
+    world
+        .spawn(0, Hex::default(), Direction::Up, Some(100_000))
+        .unwrap();
+    let logs: Vec<LogRecord> = (world.tick().into_iter())
+        .filter_map(|e| match e {
+            BotLog { log, .. } => Some(log),
+            _ => None,
+        })
+        .collect();
+    assert!(logs.len() <= sys::log::DEFAULT_CAP / sys::log::OVERHEAD + 1);
+    assert!(logs[0].msg.as_ref().ends_with("messages dropped"));
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "mock")]
+//! Game master orders applied on ticks
+
+mod common;
+use bulb::hex::{Direction, Hex};
+use common::*;
+use scalliony_engine::*;
+
+/// Spawns a bot, walls its cell and the next one, then ends the game
+const MASTER: &str = "tick world.spawn 0 0 1 0
+tick world.set 0 1 1
+tick world.set 1 0 1
+tick game.end 1";
+
+#[test]
+fn master_orders_apply_on_tick() {
+    let mut world = World::new(2, &[WAIT]);
+    let code = Bytes::from_static(MASTER.as_bytes());
+    world.ask(|p| Command::LoadMaster(code, p)).unwrap();
+    assert_eq!(world.game.bots().count(), 0);
+
+    let events = world.tick();
+    let spawn = Hex::new(0, 1);
+    let bots: Vec<_> = world.game.bots().collect();
+    assert_eq!(bots.len(), 1);
+    assert_eq!(bots[0].pid, 0u32.into());
+    assert_eq!(bots[0].facing, Direction::all()[0]);
+    assert_eq!(bots[0].at, spawn);
+    // Cells with bots are not replaced
+    assert_eq!(world.game.cell(spawn), Cell::Bot(bots[0].bid));
+    assert_eq!(world.game.cell(Hex::new(1, 0)), Cell::Wall);
+    assert!(world.game.is_over());
+    assert!(events.iter().any(|e| matches!(e, Event::BotSpawn { .. })));
+    assert_eq!(
+        events
+            .iter()
+            .filter(|e| matches!(e, Event::Cells(_)))
+            .count(),
+        1
+    );
+    assert!(events.iter().any(|e| matches!(
+        e,
+        Event::GameOver { winner: Some(w) } if u32::from(*w) == 1
+    )));
+
+    // Same orders again, on a busy cell and an ended game
+    let events = world.tick();
+    assert_eq!(world.game.bots().count(), 1);
+    assert!(events.iter().any(|e| matches!(
+        e,
+        Event::SpawnError {
+            err: SpawnErr::BusyCell,
+            ..
+        }
+    )));
+    assert!(!events.iter().any(|e| matches!(e, Event::GameOver { .. })));
+}
+
+#[test]
+fn bad_master_is_rejected() {
+    let mut world = World::new(1, &[WAIT]);
+    let code = Bytes::from_static(b"tick world.nothing");
+    let err = world.ask(|p| Command::LoadMaster(code, p)).unwrap_err();
+    assert_eq!(err.ctx.as_ref(), "Failed to start game master");
+}
@@ -0,0 +1,257 @@
+#![cfg(feature = "mock")]
+//! Bot moves resolution
+
+mod common;
+use bulb::hex::{Direction, Hex, I};
+use common::*;
+use proptest::prelude::*;
+use scalliony_engine::*;
+use std::collections::{HashMap, HashSet};
+
+const RAD: I = 2;
+
+/// Expected positions of bots alive after a tick
+///
+/// A move succeeds if no other move targets the same cell and the target is ground
+/// or the bot in front moves too, without swapping places with it.
+fn resolve(
+    world: &World,
+    before: &[BotInfo],
+    movers: &HashSet<BotId>,
+    dead: &HashSet<BotId>,
+) -> HashMap<BotId, Hex> {
+    let alive: Vec<&BotInfo> = before.iter().filter(|b| !dead.contains(&b.bid)).collect();
+    let at: HashMap<BotId, Hex> = alive.iter().map(|b| (b.bid, b.at)).collect();
+    let occupied: HashMap<Hex, BotId> = alive.iter().map(|b| (b.at, b.bid)).collect();
+    let target: HashMap<BotId, Hex> = (alive.iter())
+        .filter(|b| movers.contains(&b.bid))
+        .map(|b| (b.bid, b.at.neighbor(b.facing)))
+        .collect();
+    let mut heads: HashMap<Hex, usize> = HashMap::new();
+    for to in target.values() {
+        *heads.entry(*to).or_default() += 1;
+    }
+    let mut ok: HashSet<BotId> = (target.iter())
+        .filter(|(_, to)| heads[to] == 1)
+        .map(|(id, _)| *id)
+        .collect();
+    loop {
+        let prev = ok.clone();
+        ok.retain(|id| {
+            let to = target[id];
+            match occupied.get(&to) {
+                None => world.game.cell(to) != Cell::Wall,
+                Some(front) => prev.contains(front) && target[front] != at[id],
+            }
+        });
+        if ok.len() == prev.len() {
+            break;
+        }
+    }
+    (at.into_iter())
+        .map(|(id, at)| (id, if ok.contains(&id) { target[&id] } else { at }))
+        .collect()
+}
+
+/// Tick and compare with [`resolve`]
+fn tick_and_check(world: &mut World, movers: &HashSet<BotId>) {
+    let before: Vec<BotInfo> = world.game.bots().collect();
+    let events = world.tick();
+    let dead: HashSet<BotId> = (events.iter())
+        .filter_map(|e| match e {
+            BotDie { src } => Some(src.bid),
+            _ => None,
+        })
+        .collect();
+    let expected = resolve(world, &before, movers, &dead);
+    let after: HashMap<BotId, Hex> = world.game.bots().map(|b| (b.bid, b.at)).collect();
+    assert_eq!(after, expected);
+    world.check_map(Hex::default().range(RAD + 1));
+}
+
+/// Spawn bots by cell of the arena as `(forward, facing, dying)`
+fn run(cells: Vec<Option<(bool, Direction, bool)>>, ticks: usize) {
+    let mut world = World::new(RAD, &[WAIT, FORWARD]);
+    let mut movers = HashSet::new();
+    for (at, bot) in Hex::default().range(RAD).zip(cells) {
+        let Some((forward, facing, dying)) = bot else {
+            continue;
+        };
+        // Enough to boot but not to move
+        let fuel = dying.then_some(100);
+        let bid = world.spawn(forward as u32, at, facing, fuel).unwrap();
+        if forward {
+            movers.insert(bid);
+        }
+    }
+    world.events();
+    world.check_map(Hex::default().range(RAD + 1));
+    for _ in 0..ticks {
+        tick_and_check(&mut world, &movers);
+    }
+}
+
+fn bot() -> impl Strategy<Value = Option<(bool, Direction, bool)>> {
+    let facing = prop::sample::select(Direction::all().to_vec());
+    prop::option::weighted(
+        0.8,
+        (prop::bool::weighted(0.8), facing, prop::bool::weighted(0.1)),
+    )
+}
+
+proptest! {
+    #[test]
+    fn moves_keep_map_in_sync(cells in prop::collection::vec(bot(), Hex::default().range(RAD).len())) {
+        run(cells, 3);
+    }
+}
+
+/// Cells at distance 1 around center, each facing the next one
+fn ring() -> Vec<(Hex, Direction)> {
+    (0..6)
+        .map(|d| {
+            let at = Hex::default().neighbor(Direction::all()[d]);
+            (at, Direction::all()[(d + 2) % 6])
+        })
+        .collect()
+}
+
+fn spawn_all(world: &mut World, bots: &[(Hex, Direction)]) -> Vec<BotId> {
+    let ids = (bots.iter())
+        .map(|(at, facing)| world.spawn(1, *at, *facing, None).unwrap())
+        .collect();
+    world.events();
+    ids
+}
+fn positions(world: &World, ids: &[BotId]) -> Vec<Hex> {
+    ids.iter()
+        .map(|id| world.game.bot(*id).unwrap().at)
+        .collect()
+}
+
+#[test]
+fn rotating_ring_moves() {
+    let mut world = World::new(RAD, &[WAIT, FORWARD]);
+    let bots = ring();
+    let ids = spawn_all(&mut world, &bots);
+    world.tick();
+    let mut expected: Vec<Hex> = bots.iter().map(|(at, _)| *at).collect();
+    expected.rotate_left(1);
+    assert_eq!(positions(&world, &ids), expected);
+}
+
+#[test]
+fn rotating_triangle_moves() {
+    let mut world = World::new(RAD, &[WAIT, FORWARD]);
+    let o = Hex::default();
+    let bots = [
+        (o, Direction::Up),
+        (o.neighbor(Direction::Up), Direction::DownRight),
+        (o.neighbor(Direction::UpRight), Direction::DownLeft),
+    ];
+    let ids = spawn_all(&mut world, &bots);
+    world.tick();
+    let expected = vec![bots[1].0, bots[2].0, bots[0].0];
+    assert_eq!(positions(&world, &ids), expected);
+}
+
+#[test]
+fn swap_is_blocked() {
+    let mut world = World::new(RAD, &[WAIT, FORWARD]);
+    let o = Hex::default();
+    let bots = [
+        (o, Direction::Up),
+        (o.neighbor(Direction::Up), Direction::Down),
+    ];
+    let ids = spawn_all(&mut world, &bots);
+    let events = world.tick();
+    assert_eq!(positions(&world, &ids), vec![bots[0].0, bots[1].0]);
+    let collides = events
+        .iter()
+        .filter(|e| matches!(e, BotCollide { .. }))
+        .count();
+    assert_eq!(collides, 2);
+}
+
+#[test]
+fn head_conflict_blocks_both() {
+    let mut world = World::new(RAD, &[WAIT, FORWARD]);
+    let o = Hex::default();
+    let bots = [
+        (o.neighbor(Direction::Down), Direction::Up),
+        (o.neighbor(Direction::Up), Direction::Down),
+    ];
+    let ids = spawn_all(&mut world, &bots);
+    world.tick();
+    assert_eq!(positions(&world, &ids), vec![bots[0].0, bots[1].0]);
+    assert_eq!(world.game.cell(o), Cell::Ground);
+}
+
+#[test]
+fn chain_follows_head() {
+    let mut world = World::new(RAD, &[WAIT, FORWARD]);
+    let o = Hex::default();
+    let up = Hex::from(Direction::Up);
+    let bots = [
+        (o - up, Direction::Up),
+        (o, Direction::Up),
+        (o + up, Direction::Up),
+    ];
+    let ids = spawn_all(&mut world, &bots);
+    world.tick();
+    assert_eq!(positions(&world, &ids), vec![o, o + up, o + up * 2]);
+    // Head is now against the wall
+    world.tick();
+    assert_eq!(positions(&world, &ids), vec![o, o + up, o + up * 2]);
+    world.check_map(Hex::default().range(RAD + 1));
+}
+
+/// Logs `boot` once booted and `hit` when blocked
+const HOOKS: &str = "data 0 boothit
+on_boot io@1.log 0 4
+on_collide io@1.log 4 3";
+
+/// Messages of bot logs
+fn logs(events: &[Event]) -> Vec<(BotId, String)> {
+    (events.iter())
+        .filter_map(|e| match e {
+            BotLog { src, log } => Some((src.bid, log.msg.to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn on_boot_runs_once() {
+    let mut world = World::new(RAD, &[&format!("{}\n{}", HOOKS, FORWARD)]);
+    let bid = world.spawn(0, Hex::default(), Direction::Up, None).unwrap();
+    let mut events = world.events();
+    for _ in 0..4 {
+        events.extend(world.tick());
+    }
+    let boots: Vec<_> = (logs(&events).into_iter())
+        .filter(|(_, msg)| msg == "boot")
+        .collect();
+    assert_eq!(boots, [(bid, "boot".to_string())]);
+}
+
+#[test]
+fn on_collide_runs_when_blocked() {
+    let (wait, forward) = (
+        format!("{}\n{}", HOOKS, WAIT),
+        format!("{}\n{}", HOOKS, FORWARD),
+    );
+    let mut world = World::new(RAD, &[&wait, &forward]);
+    let o = Hex::default();
+    let up = Hex::from(Direction::Up);
+    let idle = world.spawn(0, o + up * 2, Direction::Down, None).unwrap();
+    let mover = spawn_all(&mut world, &[(o, Direction::Up)])[0];
+    let events = world.tick();
+    assert!(!logs(&events).iter().any(|(_, msg)| msg == "hit"));
+    // Then blocked by the idle bot, which is not
+    for _ in 0..2 {
+        let events = world.tick();
+        assert_eq!(logs(&events), [(mover, "hit".to_string())]);
+    }
+    assert_eq!(positions(&world, &[mover, idle]), [o + up, o + up * 2]);
+}
@@ -0,0 +1,104 @@
+#![cfg(feature = "mock")]
+//! Scenario objectives and game over
+
+mod common;
+use bulb::hex::{Direction, Hex};
+use common::*;
+use scalliony_engine::scenario::{Objective, Scenario, ScenarioBot};
+use scalliony_engine::*;
+
+fn bot(at: Hex) -> ScenarioBot {
+    ScenarioBot {
+        program: 0,
+        at,
+        facing: Direction::Up,
+    }
+}
+fn scenario(program: &str, players: Vec<Vec<ScenarioBot>>, objective: Objective) -> Scenario {
+    let mut scenario = arena(2, &[program]);
+    scenario.players = players;
+    scenario.objectives = vec![objective];
+    scenario
+}
+/// Tick until game over, at most `ticks` times
+fn winner(world: &mut World, ticks: usize) -> Option<Option<PlayerId>> {
+    for _ in 0..ticks {
+        for event in world.tick() {
+            if let GameOver { winner } = event {
+                return Some(winner);
+            }
+        }
+    }
+    None
+}
+
+#[test]
+fn reach_hex_wins() {
+    let to = Hex::default().neighbor(Direction::Up);
+    let mut world = World::load(scenario(
+        FORWARD,
+        vec![
+            vec![bot(Hex::default())],
+            vec![bot(Hex::default().neighbor(Direction::Down))],
+        ],
+        Objective::ReachHex { at: to },
+    ));
+    assert_eq!(winner(&mut world, 5), Some(Some(0.into())));
+    // Game stays over
+    assert_eq!(winner(&mut world, 2), None);
+}
+
+#[test]
+fn reach_hex_needs_own_bot() {
+    let to = Hex::default().neighbor(Direction::Up);
+    let mut world = World::load(scenario(
+        WAIT,
+        vec![vec![bot(Hex::default())], vec![bot(to)]],
+        Objective::ReachHex { at: to },
+    ));
+    assert_eq!(winner(&mut world, 1), Some(Some(1.into())));
+}
+
+#[test]
+fn last_player_standing_needs_rivals() {
+    let mut world = World::load(scenario(
+        WAIT,
+        vec![vec![bot(Hex::default())]],
+        Objective::LastPlayerStanding,
+    ));
+    assert_eq!(winner(&mut world, 3), None);
+}
+
+#[test]
+fn last_player_standing_wins() {
+    let mut world = World::load(scenario(
+        WAIT,
+        vec![
+            vec![bot(Hex::default())],
+            vec![bot(Hex::default().neighbor(Direction::Up))],
+        ],
+        Objective::LastPlayerStanding,
+    ));
+    assert_eq!(winner(&mut world, 2), None);
+    let rival = (world.game.bots())
+        .find(|b| b.at != Hex::default())
+        .unwrap()
+        .bid;
+    world.ask(|p| Command::Despawn(rival, p)).unwrap();
+    assert_eq!(winner(&mut world, 1), Some(Some(0.into())));
+}
+
+#[test]
+fn time_limit_ends_without_winner() {
+    let mut scenario = scenario(
+        WAIT,
+        vec![
+            vec![bot(Hex::default())],
+            vec![bot(Hex::default().neighbor(Direction::Up))],
+        ],
+        Objective::LastPlayerStanding,
+    );
+    scenario.time_limit = Some(3);
+    let mut world = World::load(scenario);
+    assert_eq!(winner(&mut world, 5), Some(None));
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "mock")]
+//! Same events whether bots tick in parallel or not
+//!
+//! Run with and without the `parallel` feature: both must match [`DIGEST`].
+
+mod common;
+use bulb::hex::{Direction, Hex};
+use common::*;
+use scalliony_engine::scenario::ScenarioBot;
+use scalliony_engine::*;
+
+const PROGRAMS: [&str; 4] = [
+    "data 0 hi\ntick io@1.log 0 2\ntick motor@1.forward",
+    "tick motor@1.left\ntick motor@1.forward",
+    "data 0 kv\ntick storage@1.set 0 1 1 1\ntick motor@1.right\ntick motor@1.forward",
+    "tick sys@1.random\ntick trap boom",
+];
+/// FNV-1a of event `Debug` lines, update when the scenario or events change
+const DIGEST: u64 = 0x1c47b0fdf9b30925;
+
+/// Crowded arena with bots of every program for both players
+fn crowd() -> Vec<String> {
+    let mut scenario = arena(3, &PROGRAMS);
+    scenario.players = vec![Vec::new(), Vec::new()];
+    let cells = Hex::default().range(3).step_by(2);
+    for (i, at) in cells.enumerate() {
+        scenario.players[i % 2].push(ScenarioBot {
+            program: (i % PROGRAMS.len()) as u32,
+            at,
+            facing: Direction::all()[i % 6],
+        });
+    }
+    let mut world = World::load(scenario);
+    let mut lines = Vec::new();
+    for _ in 0..20 {
+        lines.extend(world.tick().iter().map(|e| match e {
+            // Without wall clock time
+            Event::TickStart { tid, .. } => format!("TickStart {}", u32::from(*tid)),
+            e => format!("{:?}", e),
+        }));
+        world.check_map(Hex::default().range(3));
+    }
+    lines
+}
+
+fn digest(lines: &[String]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in lines.iter().flat_map(|l| l.bytes().chain(*b"\n")) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[test]
+fn same_events_across_runs() {
+    let lines = crowd();
+    assert!(lines.iter().any(|l| l.starts_with("BotCollide")));
+    assert_eq!(lines, crowd());
+    assert_eq!(digest(&lines), DIGEST, "{:#x}", digest(&lines));
+}
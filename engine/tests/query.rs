@@ -0,0 +1,67 @@
+#![cfg(feature = "mock")]
+//! Read-only inspection of game state
+
+mod common;
+use bulb::hex::{Direction, Hex};
+use common::*;
+use scalliony_engine::*;
+
+#[test]
+fn spawned_bot_is_visible() {
+    let mut world = World::new(2, &[FORWARD]);
+    let at = Hex::default();
+    let bid = world.spawn(0, at, Direction::Up, None).unwrap();
+
+    let info = world.game.bot(bid).unwrap();
+    assert_eq!(info.bid, bid);
+    assert_eq!(info.pid, 0u32.into());
+    assert_eq!(info.player, None);
+    assert_eq!(info.at, at);
+    assert_eq!(info.facing, Direction::Up);
+    assert!(!info.running);
+    assert_eq!(world.game.cell(at), Cell::Bot(bid));
+    assert_eq!(world.game.bots().count(), 1);
+    assert_eq!(world.game.program(0u32.into()).unwrap().bots, 1);
+
+    let tick = world.game.tick_id();
+    world.tick();
+    assert_eq!(u32::from(world.game.tick_id()), u32::from(tick) + 1);
+    let info = world.game.bot(bid).unwrap();
+    assert!(info.running);
+    assert_eq!(info.at, at.neighbor(Direction::Up));
+    assert_eq!(world.game.cell(at), Cell::Ground);
+    assert_eq!(world.game.cell(info.at), Cell::Bot(bid));
+}
+
+#[test]
+fn commands_match_getters() {
+    let mut world = World::new(2, &[WAIT]);
+    let at = Hex::default().neighbor(Direction::Down);
+    let bid = world.spawn(0, at, Direction::Down, None).unwrap();
+    world.tick();
+
+    let info = world.ask(|p| Command::Bot(bid, p)).unwrap();
+    assert_eq!(info.at, at);
+    assert_eq!(info.facing, Direction::Down);
+    let bots = world.ask(Command::Bots);
+    assert_eq!(bots.len(), 1);
+    assert_eq!(bots[0].bid, bid);
+    assert_eq!(world.ask(|p| Command::Cell(at, p)), Cell::Bot(bid));
+    let program = world.ask(|p| Command::Program(0u32.into(), p)).unwrap();
+    assert_eq!(program.bots, 1);
+    assert_eq!(
+        u32::from(world.ask(Command::TickId)),
+        u32::from(world.game.tick_id())
+    );
+}
+
+#[test]
+fn unknown_ids_are_none() {
+    let mut world = World::new(1, &[WAIT]);
+    let bid = world.spawn(0, Hex::default(), Direction::Up, None).unwrap();
+    world.ask(|p| Command::Despawn(bid, p)).unwrap();
+    assert!(world.game.bot(bid).is_none());
+    assert!(world.ask(|p| Command::Bot(bid, p)).is_none());
+    assert_eq!(world.game.cell(Hex::default()), Cell::Ground);
+    assert!(world.game.program(1u32.into()).is_none());
+}
@@ -0,0 +1,68 @@
+#![cfg(not(feature = "mock"))]
+//! Bot randomness seeded by world and bot
+
+mod common;
+use bulb::hex::{Direction, Hex};
+use common::*;
+use scalliony_engine::*;
+
+/// Logs in hex a `sys.random` value then 8 bytes of wasi `random_get`
+const RANDOM: &str = r#"(module
+  (import "sys@1" "random" (func $random (result i64)))
+  (import "io@1" "log" (func $log (param i32 i32)))
+  (import "wasi_snapshot_preview1" "random_get" (func $random_get (param i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (func $digit (param $x i32) (result i32)
+    (i32.add (local.get $x)
+      (select (i32.const 48) (i32.const 87) (i32.lt_u (local.get $x) (i32.const 10)))))
+  (func $hex (local $i i32) (local $b i32)
+    (loop $next
+      (local.set $b (i32.load8_u (local.get $i)))
+      (i32.store8 (i32.add (i32.const 16) (i32.shl (local.get $i) (i32.const 1)))
+        (call $digit (i32.shr_u (local.get $b) (i32.const 4))))
+      (i32.store8 (i32.add (i32.const 17) (i32.shl (local.get $i) (i32.const 1)))
+        (call $digit (i32.and (local.get $b) (i32.const 15))))
+      (local.set $i (i32.add (local.get $i) (i32.const 1)))
+      (br_if $next (i32.lt_u (local.get $i) (i32.const 8))))
+    (call $log (i32.const 16) (i32.const 16)))
+  (func (export "tick")
+    (i64.store (i32.const 0) (call $random))
+    (call $hex)
+    (drop (call $random_get (i32.const 0) (i32.const 8)))
+    (call $hex)))"#;
+
+/// `sys.random` and `random_get` outputs of each bot in a world of `seed`
+fn draws(seed: u32) -> Vec<(String, String)> {
+    let mut scenario = arena(1, &[RANDOM]);
+    scenario.map.seed = seed;
+    let mut world = World::load(scenario);
+    let bids: Vec<BotId> = [Direction::Up, Direction::Down]
+        .into_iter()
+        .map(|d| world.spawn(0, Hex::from(d), d, None).unwrap())
+        .collect();
+    let events = world.tick();
+    bids.into_iter()
+        .map(|bid| {
+            let logs: Vec<String> = (events.iter())
+                .filter_map(|e| match e {
+                    Event::BotLog { src, log } if src.bid == bid => Some(log.msg.to_string()),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(logs.len(), 2, "{:?}", events);
+            (logs[0].clone(), logs[1].clone())
+        })
+        .collect()
+}
+
+#[test]
+fn randomness_is_deterministic() {
+    let draws = draws(42);
+    assert_eq!(draws, self::draws(42));
+    for (sys, wasi) in draws.iter() {
+        assert_eq!(sys.len(), 16);
+        assert_ne!(sys, wasi);
+    }
+    assert_ne!(draws[0], draws[1]);
+    assert_ne!(draws, self::draws(43));
+}
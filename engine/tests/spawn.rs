@@ -0,0 +1,83 @@
+#![cfg(feature = "mock")]
+//! Bot creation by players and scenarios
+
+mod common;
+use bulb::hex::{Direction, Hex};
+use common::*;
+use scalliony_engine::scenario::ScenarioBot;
+use scalliony_engine::*;
+
+#[test]
+fn spawn_fuel_is_bounded() {
+    let mut world = World::new(1, &[WAIT]);
+    let at = Hex::default();
+    assert!(matches!(
+        world.spawn(0, at, Direction::Up, Some(63)),
+        Err(SpawnErr::NotEnoughFuel { min: 64 })
+    ));
+    assert!(matches!(
+        world.spawn(0, at, Direction::Up, Some(100_001)),
+        Err(SpawnErr::TooMuchFuel { max: 100_000 })
+    ));
+    assert_eq!(world.game.cell(at), Cell::Ground);
+    assert!(world.spawn(0, at, Direction::Up, Some(64)).is_ok());
+}
+
+#[test]
+fn bad_scenario_is_rejected() {
+    let mut world = World::new(1, &[WAIT]);
+    let bid = world.spawn(0, Hex::default(), Direction::Up, None).unwrap();
+
+    let mut scenario = arena(1, &[WAIT]);
+    scenario.players = vec![vec![ScenarioBot {
+        program: 1,
+        at: Hex::default(),
+        facing: Direction::Up,
+    }]];
+    let err = world
+        .ask(|p| Command::LoadScenario(Box::new(scenario), p))
+        .unwrap_err();
+    assert_eq!(err.ctx.as_ref(), "Bad scenario");
+    assert!(world.game.bot(bid).is_some());
+}
+
+#[test]
+fn failed_scenario_compile_keeps_game() {
+    let mut world = World::new(1, &[WAIT]);
+    let bid = world.spawn(0, Hex::default(), Direction::Up, None).unwrap();
+
+    let err = world
+        .load_scenario(arena(2, &[WAIT, "tick nope@1.nothing"]))
+        .unwrap_err();
+    assert_eq!(err.ctx.as_ref(), "Failed to compile");
+    assert!(world.game.bot(bid).is_some());
+    assert!(world.game.program(1u32.into()).is_none());
+    assert_eq!(
+        world.game.cell(
+            Hex::default()
+                .neighbor(Direction::Up)
+                .neighbor(Direction::Up)
+        ),
+        Cell::Wall
+    );
+}
+
+#[test]
+fn scenario_load_keeps_removed_bot_stats() {
+    let mut world = World::new(2, &[FORWARD]);
+    world.spawn(0, Hex::default(), Direction::Up, None).unwrap();
+    world.tick();
+    world.tick();
+
+    world.load_scenario(arena(1, &[WAIT])).unwrap();
+    assert!(world
+        .events()
+        .iter()
+        .any(|e| matches!(e, Event::BotDie { .. })));
+    assert_eq!(world.game.bots().count(), 0);
+    let metrics = world.ask(Command::Metrics);
+    let stats = &metrics.programs[0].stats;
+    assert_eq!(stats.ticks, 2);
+    assert_eq!(stats.distance, 2);
+    assert_eq!(metrics.programs.len(), 2);
+}
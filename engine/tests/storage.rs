@@ -0,0 +1,50 @@
+#![cfg(feature = "mock")]
+//! Program storage shared by bots
+
+mod common;
+use bulb::hex::{Direction, Hex};
+use common::*;
+use scalliony_engine::scenario::ScenarioBot;
+
+/// Stores `k=v` then `me=v`
+const SET: &str = "data 0 kvme\ntick storage@1.set 0 1 1 1\ntick storage@1.set 2 2 1 1";
+
+#[test]
+fn storage_is_shared_by_owner() {
+    let mut scenario = arena(2, &[SET]);
+    let bot = |q| ScenarioBot {
+        program: 0,
+        at: Hex::new(q, 0),
+        facing: Direction::Up,
+    };
+    scenario.players = vec![vec![bot(-1), bot(0)], vec![bot(1)]];
+    let mut world = World::load(scenario);
+    world.spawn(0, Hex::new(0, 1), Direction::Up, None).unwrap();
+    world.tick();
+    let info = world.game.program(0u32.into()).unwrap();
+    // Same keys in buckets of both players and of unowned bots
+    assert_eq!(info.storage, 3 * 5);
+}
+
+#[test]
+fn overwrites_are_not_counted_twice() {
+    // Fills the bucket with keys `a`, `aa`... then shrinks the value of `a`
+    let mut code = format!("data 0 {}\n", "a".repeat(1024));
+    let mut size = 0;
+    for len in 1..=62 {
+        code.push_str(&format!("tick storage@1.set 0 {} 0 1024\n", len));
+        size += len + 1024;
+    }
+    code.push_str("tick storage@1.set 0 63 0 32\n");
+    size += 63 + 32;
+    assert_eq!(size, 64 * 1024);
+    code.push_str("tick storage@1.set 0 1 0 1");
+    let mut world = World::new(1, &[&code]);
+    // Writes are charged by length
+    world
+        .spawn(0, Hex::default(), Direction::Up, Some(100_000))
+        .unwrap();
+    world.tick();
+    let info = world.game.program(0u32.into()).unwrap();
+    assert_eq!(info.storage, size - 1024 + 1);
+}
@@ -6,13 +6,14 @@ use axum::{
         ws::{Message, WebSocket},
         Query, WebSocketUpgrade,
     },
+    http::StatusCode,
     middleware::from_extractor,
     response::{
         sse::{self, Sse},
         IntoResponse,
     },
     routing::get,
-    Extension, Router,
+    Extension, Json, Router,
 };
 use futures::{stream::Stream, SinkExt};
 use lazy_static::lazy_static;
@@ -33,6 +34,7 @@ pub fn router(interface: InterfaceRef) -> Router {
     Router::new()
         .route("/sse", get(sse))
         .route("/ws", get(ws_upgrade))
+        .route("/metrics", get(metrics))
         .route_layer(from_extractor::<auth::User>())
         .layer(Extension(interface))
 }
@@ -52,36 +54,46 @@ async fn ws(socket: WebSocket, interface: InterfaceRef, user: auth::Claims) {
     let (tx_self, mut rx_self) = tokio::sync::mpsc::unbounded_channel();
 
     tracing::debug!("connected");
-    let is_admin = ADMIN.contains(&user.to_string());
+    let user_id: UserId = user.to_string().into();
+    let is_admin = ADMIN.contains(user_id.as_ref());
 
     let mut rx = interface.events.resubscribe();
     let read_view = view.clone();
     let mut send_task = tokio::spawn(async move {
-        while let Ok(event) = rx.recv().await {
-            if event
-                .src()
-                .map_or(true, |src| read_view.lock().unwrap().contains(src.at))
-            {
-                let json = serde_json::to_string(&event).unwrap();
-                if sender.feed(Message::Text(json)).await.is_err() {
-                    return;
-                }
-            }
-            match event {
-                TickStart { .. } => {
-                    while let Ok(event) = rx_self.try_recv() {
+        // Events are flushed together at the end of each tick
+        let mut in_tick = false;
+        loop {
+            tokio::select! {
+                event = rx.recv() => {
+                    let Ok(event) = event else {
+                        return;
+                    };
+                    if (is_admin || !event.is_debug())
+                        && event
+                            .src()
+                            .map_or(true, |src| read_view.lock().unwrap().contains(src.at))
+                    {
                         let json = serde_json::to_string(&event).unwrap();
                         if sender.feed(Message::Text(json)).await.is_err() {
                             return;
                         }
                     }
+                    match event {
+                        TickStart { .. } => in_tick = true,
+                        TickEnd => in_tick = false,
+                        _ => {}
+                    }
+                    if !in_tick && sender.flush().await.is_err() {
+                        return;
+                    }
                 }
-                TickEnd => {
-                    if sender.flush().await.is_err() {
+                // Replies do not wait for ticks, which may be paused
+                Some(reply) = rx_self.recv() => {
+                    let json = serde_json::to_string(&reply).unwrap();
+                    if sender.send(Message::Text(json)).await.is_err() {
                         return;
                     }
                 }
-                _ => {}
             }
         }
     });
@@ -91,36 +103,37 @@ async fn ws(socket: WebSocket, interface: InterfaceRef, user: auth::Claims) {
         while let Some(Ok(message)) = receiver.next().await {
             tracing::trace!(?message);
             if let Message::Text(text) = message {
-                match serde_json::from_str(&text) {
+                let reply = |rid, result| _ = tx_self.send(Reply { rid, result });
+                match serde_json::from_str::<Request>(&text) {
                     //FIXME: rate limit tx.send
-                    Ok(command) => match command {
-                        Rpc::SetView(v) => *view.lock().unwrap() = v,
-                        Rpc::Map(q) => {
-                            let tx_self = tx_self.clone();
-                            _ = tx.send(Command::Map(
-                                q,
-                                Promise::new(move |cr| {
-                                    _ = tx_self.send(Cells(cr));
-                                }),
-                            ));
-                        }
-                        Rpc::Spawn(q) => _ = tx.send(Command::Spawn(q)),
-                        Rpc::ChangeState(v) if is_admin => _ = tx.send(Command::ChangeState(v)),
-                        Rpc::ChangeState { .. } => return tracing::trace!("not admin"),
-                        Rpc::Compile { cid, code } => {
-                            let tx_self = tx_self.clone();
-                            _ = tx.send(Command::Compile(
-                                code,
-                                Promise::new(move |r| {
-                                    _ = tx_self.send(match r {
-                                        Ok(pid) => Event::ProgramAdd { cid, pid },
-                                        Err(err) => Event::CompileError { cid, err },
-                                    })
-                                }),
-                            ));
+                    Ok(Request {
+                        rid,
+                        rpc: Rpc::SetView(v),
+                    }) => {
+                        *view.lock().unwrap() = v;
+                        reply(rid, Ok(Answer::Done));
+                    }
+                    Ok(req) if req.rpc.is_admin() && !is_admin => {
+                        tracing::trace!("not admin");
+                        reply(req.rid, Err(ReplyErr::Denied));
+                    }
+                    Ok(req) => {
+                        let tx_self = tx_self.clone();
+                        if let Some(command) = req.into_command(user_id.clone(), move |e| {
+                            _ = tx_self.send(e);
+                        }) {
+                            _ = tx.send(command);
                         }
-                    },
-                    Err(err) => return tracing::trace!("serde: {}", err),
+                    }
+                    Err(err) => {
+                        tracing::trace!("serde: {}", err);
+                        let rid = serde_json::from_str::<serde_json::Value>(&text)
+                            .ok()
+                            .and_then(|v| v.get("rid")?.as_u64())
+                            .unwrap_or_default();
+                        let err = err.to_string().into();
+                        reply(rid as RequestId, Err(ReplyErr::Bad { err }));
+                    }
                 }
             }
         }
@@ -137,13 +150,30 @@ async fn ws(socket: WebSocket, interface: InterfaceRef, user: auth::Claims) {
     tracing::debug!("disconnected");
 }
 
+async fn metrics(
+    Extension(interface): Extension<InterfaceRef>,
+) -> Result<Json<Metrics>, StatusCode> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    interface
+        .commands
+        .send(Command::Metrics(Promise::new(move |metrics| {
+            _ = tx.send(metrics);
+        })))
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    rx.await
+        .map(Json)
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)
+}
+
 async fn sse(
     query: Query<Viewer>,
     Extension(interface): Extension<InterfaceRef>,
 ) -> Sse<impl Stream<Item = Result<sse::Event, Infallible>>> {
     let stream = BroadcastStream::new(interface.events.resubscribe())
         .map(Result::unwrap)
-        .filter(move |event| event.src().map_or(true, |src| query.view.contains(src.at)))
+        .filter(move |event| {
+            !event.is_debug() && event.src().map_or(true, |src| query.view.contains(src.at))
+        })
         .map(|event| Ok(sse::Event::default().json_data(event).unwrap()));
 
     Sse::new(stream).keep_alive(axum::response::sse::KeepAlive::new())
@@ -1,34 +1,61 @@
 pub use engine::*;
-use std::{thread, time::Duration};
-use tokio::sync::{broadcast, mpsc};
+use std::{sync::mpsc, thread};
+use tokio::sync::broadcast;
 
 pub fn run() -> InterfaceRef {
     let paused = std::env::var("GAME_PAUSED").map_or(false, |v| {
         v.parse()
             .unwrap_or_else(|_| v.parse::<u8>().expect("Expect a bool for GAME_PAUSED") != 0)
     });
-    let tick_time = Duration::from_millis(
-        std::env::var("GAME_TICK_MS").map_or(DEFAULT_TICK_DURATION_MS, |v| {
-            v.parse().expect("Expect an integer for GAME_TICK_MS")
-        }),
-    );
+    let tick_ms = std::env::var("GAME_TICK_MS").map_or(DEFAULT_TICK_DURATION_MS, |v| {
+        v.parse().expect("Expect an integer for GAME_TICK_MS")
+    });
 
-    let (commands_tx, mut commands_rx) = mpsc::unbounded_channel();
+    let (commands_tx, commands_rx) = mpsc::channel();
+    if let Ok(policy) = std::env::var("GAME_CRASH_POLICY") {
+        let policy: CrashPolicy =
+            serde_json::from_str(&policy).expect("Expect a valid GAME_CRASH_POLICY");
+        _ = commands_tx.send(Command::SetCrashPolicy(policy));
+    }
+    if let Ok(path) = std::env::var("GAME_SCENARIO") {
+        let file = std::fs::File::open(&path).expect("Expect a readable GAME_SCENARIO file");
+        let scenario: scenario::Scenario = serde_json::from_reader(std::io::BufReader::new(file))
+            .expect("Expect a valid GAME_SCENARIO file");
+        tracing::info!(name = %scenario.name, "loading scenario");
+        _ = commands_tx.send(Command::LoadScenario(
+            Box::new(scenario),
+            Promise::new(|res: LoadRes| {
+                if let Err(err) = res {
+                    tracing::error!(?err, "bad scenario");
+                }
+            }),
+        ));
+    }
+    if let Ok(path) = std::env::var("GAME_MASTER") {
+        let code = std::fs::read(&path).expect("Expect a readable GAME_MASTER file");
+        tracing::info!(%path, "loading game master");
+        _ = commands_tx.send(Command::LoadMaster(
+            code.into(),
+            Promise::new(|res: LoadRes| {
+                if let Err(err) = res {
+                    tracing::error!(?err, "bad game master");
+                }
+            }),
+        ));
+    }
     let (events_tx, events_rx) = broadcast::channel(128);
 
+    // Commands are received while waiting for next tick
     let mut game = GameState::new(
-        move || commands_rx.try_recv().ok(),
+        || None,
         move |v: Event| _ = events_tx.send(v),
         paused,
+        tick_ms,
     );
 
     let thread = thread::Builder::new()
         .name("game-master".into())
-        .spawn(move || {
-            while game.update() != State::Stopped {
-                thread::sleep(tick_time)
-            }
-        })
+        .spawn(move || game.run(|timeout| commands_rx.recv_timeout(timeout)))
         .unwrap();
 
     std::sync::Arc::new(Interface {
@@ -39,7 +66,7 @@ pub fn run() -> InterfaceRef {
 }
 
 pub struct Interface {
-    pub commands: mpsc::UnboundedSender<Command>,
+    pub commands: mpsc::Sender<Command>,
     pub events: broadcast::Receiver<Event>,
     thread: Option<thread::JoinHandle<()>>,
 }
@@ -48,7 +75,9 @@ impl Drop for Interface {
     fn drop(&mut self) {
         if let Some(handle) = self.thread.take() {
             tracing::debug!("exit");
-            _ = self.commands.send(Command::ChangeState(State::Stopped));
+            _ = self
+                .commands
+                .send(Command::ChangeState(State::Stopped, Promise::new(|_| {})));
             handle.join().unwrap();
         }
         tracing::trace!("bye");
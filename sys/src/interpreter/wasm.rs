@@ -0,0 +1,274 @@
+pub use crate::spec::wasm as spec;
+use anyhow::{anyhow, bail, Result};
+use spec::Value;
+use wasmi::core::Trap;
+
+pub struct Engine(wasmi::Engine);
+impl Engine {
+    pub fn new() -> Self {
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+        Self(wasmi::Engine::new(&config))
+    }
+}
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[repr(transparent)]
+pub struct RawStore<T>(T);
+impl<T> spec::Store for RawStore<T> {
+    type T = T;
+
+    #[inline]
+    fn new(state: T) -> Self {
+        Self(state)
+    }
+    #[inline]
+    fn state(&self) -> &T {
+        &self.0
+    }
+    #[inline]
+    fn state_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+pub use crate::wasi::{WasiProfile, WasiStore};
+
+pub struct Linker<S>(wasmi::Linker<S>, Vec<spec::LinkExport>, spec::Imports);
+impl<S: spec::Store + 'static> spec::Linker<S> for Linker<S> {
+    type Engine = Engine;
+    type Template = Template<S>;
+
+    fn new(engine: &Engine) -> Self {
+        Self(
+            wasmi::Linker::new(&engine.0),
+            Vec::new(),
+            spec::Imports::default(),
+        )
+    }
+    fn add_dyn_func(
+        &mut self,
+        module: &str,
+        name: &str,
+        sig: spec::Signature,
+        func: Box<spec::DynHostFunc<S>>,
+    ) -> Result<&mut Self> {
+        let ty = wasmi::FuncType::new(
+            sig.params.iter().map(|t| val_type(*t)),
+            sig.results.iter().map(|t| val_type(*t)),
+        );
+        let results = sig.results.clone();
+        self.0
+            .func_new(module, name, ty, move |caller, params, out| {
+                let params = params
+                    .iter()
+                    .map(from_val)
+                    .collect::<Result<Vec<_>>>()
+                    .map_err(trap)?;
+                let mut values: Vec<_> = results.iter().copied().map(Value::default_of).collect();
+                func(&mut HostCaller(caller), &params, &mut values).map_err(trap)?;
+                for (slot, v) in out.iter_mut().zip(values) {
+                    *slot = into_val(v);
+                }
+                Ok(())
+            })?;
+        self.2.define(module, name, sig);
+        Ok(self)
+    }
+    #[inline]
+    fn add_export(&mut self, v: spec::LinkExport) -> &mut Self {
+        self.1.push(v);
+        self
+    }
+    #[inline]
+    fn imports(&self) -> &spec::Imports {
+        &self.2
+    }
+
+    #[inline]
+    fn link(&self, bytes: &[u8]) -> Result<Template<S>> {
+        Template::new(self, bytes)
+    }
+}
+impl<T: 'static> Linker<WasiStore<T>> {
+    pub fn add_wasi(&mut self) -> &mut Self {
+        crate::wasi::add_to_linker(self).unwrap();
+        self.2.unversioned(crate::wasi::MODULE);
+        self
+    }
+}
+
+#[cold]
+fn trap(err: anyhow::Error) -> Trap {
+    match err.downcast::<Trap>() {
+        Ok(trap) => trap,
+        Err(err) => Trap::new(format!("{:#}", err)),
+    }
+}
+#[inline]
+fn val_type(t: spec::ValType) -> wasmi::core::ValueType {
+    match t {
+        spec::ValType::I32 => wasmi::core::ValueType::I32,
+        spec::ValType::I64 => wasmi::core::ValueType::I64,
+    }
+}
+#[inline]
+fn signature(ty: &wasmi::FuncType) -> Option<spec::Signature> {
+    fn types(it: &[wasmi::core::ValueType]) -> Option<Vec<spec::ValType>> {
+        it.iter()
+            .map(|t| match t {
+                wasmi::core::ValueType::I32 => Some(spec::ValType::I32),
+                wasmi::core::ValueType::I64 => Some(spec::ValType::I64),
+                _ => None,
+            })
+            .collect()
+    }
+    Some(spec::Signature {
+        params: types(ty.params())?,
+        results: types(ty.results())?,
+    })
+}
+#[inline]
+fn from_val(v: &wasmi::Value) -> Result<Value> {
+    match v {
+        wasmi::Value::I32(v) => Ok(Value::I32(*v)),
+        wasmi::Value::I64(v) => Ok(Value::I64(*v)),
+        _ => bail!("Unsupported value type {:?}", v.ty()),
+    }
+}
+#[inline]
+fn into_val(v: Value) -> wasmi::Value {
+    match v {
+        Value::I32(v) => wasmi::Value::I32(v),
+        Value::I64(v) => wasmi::Value::I64(v),
+    }
+}
+
+/// Module validated with linker
+pub struct Template<S> {
+    module: wasmi::Module,
+    linker: wasmi::Linker<S>,
+}
+impl<S: spec::Store> Template<S> {
+    fn new(linker: &Linker<S>, bytes: &[u8]) -> Result<Self> {
+        let bytes = wat::parse_bytes(bytes)?;
+        let module = wasmi::Module::new(linker.0.engine(), &bytes[..])?;
+        let mut issues: Vec<_> = module
+            .imports()
+            .filter_map(|import| {
+                let found = match import.ty() {
+                    wasmi::ExternType::Func(ty) => signature(ty),
+                    _ => None,
+                };
+                linker.2.check(import.module(), import.name(), found)
+            })
+            .collect();
+        issues.extend(
+            linker
+                .2
+                .check_versions(module.imports().map(|import| import.module())),
+        );
+        issues.extend(spec::check_exports(&linker.1, |name| {
+            module.get_export(name).map(|ex| match ex {
+                wasmi::ExternType::Func(ty) => signature(&ty),
+                _ => None,
+            })
+        }));
+        spec::LinkError::check(issues)?;
+        Ok(Self {
+            module,
+            linker: linker.0.clone(),
+        })
+    }
+}
+
+pub struct Instance<S>(wasmi::Instance, wasmi::Store<S>);
+impl<S: spec::Store> spec::Instance<S> for Instance<S> {
+    type Template = Template<S>;
+    type Func = wasmi::Func;
+
+    fn new(tpl: &Template<S>, data: S::T, fuel: u64) -> Result<Self> {
+        let mut store = wasmi::Store::new(tpl.module.engine(), S::new(data));
+        store.add_fuel(fuel).map_err(|err| anyhow!("{}", err))?;
+        let i = tpl
+            .linker
+            .instantiate(&mut store, &tpl.module)
+            .and_then(|pre| pre.start(&mut store))?;
+        Ok(Self(i, store))
+    }
+
+    #[inline]
+    fn store(&self) -> &S {
+        self.1.data()
+    }
+    #[inline]
+    fn store_mut(&mut self) -> &mut S {
+        self.1.data_mut()
+    }
+
+    fn get_dyn_func(&mut self, name: &str, sig: &spec::Signature) -> Result<wasmi::Func> {
+        let Some(f) = self.0.get_func(&self.1, name) else {
+            bail!("Missing '{}' export", name)
+        };
+        if signature(&f.ty(&self.1)).as_ref() != Some(sig) {
+            bail!("'{}' function signature is {}", name, sig)
+        }
+        Ok(f)
+    }
+    #[inline]
+    fn call_dyn(&mut self, f: &wasmi::Func, params: &[Value], results: &mut [Value]) -> Result<()> {
+        let params: Vec<_> = params.iter().copied().map(into_val).collect();
+        let mut vals: Vec<_> = results.iter().copied().map(into_val).collect();
+        f.call(&mut self.1, &params, &mut vals)?;
+        for (slot, v) in results.iter_mut().zip(vals.iter()) {
+            *slot = from_val(v)?;
+        }
+        Ok(())
+    }
+
+    fn add_fuel(&mut self, v: u64) -> Result<(), std::num::TryFromIntError> {
+        i64::try_from(self.fuel().saturating_add(v))?;
+        self.1.add_fuel(v).unwrap();
+        Ok(())
+    }
+    #[inline]
+    fn consume_fuel(&mut self, v: u64) -> Result<u64> {
+        self.1.consume_fuel(v).map_err(|err| anyhow!("{}", err))
+    }
+}
+
+pub use anyhow::Error;
+pub use spec::MemoryOutOfBoundsError;
+pub type Func<P, R> = spec::TypedFunc<wasmi::Func, P, R>;
+
+/// Message and frames of a trap, backtraces are not available
+pub fn split_backtrace(err: &Error) -> (String, Vec<spec::Frame>) {
+    (format!("{:#}", err), Vec::new())
+}
+
+#[repr(transparent)]
+struct HostCaller<'a, S>(wasmi::Caller<'a, S>);
+impl<S> spec::Caller<S> for HostCaller<'_, S> {
+    #[inline]
+    fn store(&self) -> &S {
+        self.0.data()
+    }
+    #[inline]
+    fn store_mut(&mut self) -> &mut S {
+        self.0.data_mut()
+    }
+    #[inline]
+    fn consume_fuel(&mut self, v: u64) -> Result<u64> {
+        self.0.consume_fuel(v).map_err(|err| anyhow!("{}", err))
+    }
+    fn memory(&mut self) -> Result<(&mut [u8], &mut S)> {
+        match self.0.get_export("memory") {
+            Some(wasmi::Extern::Memory(m)) => Ok(m.data_and_store_mut(&mut self.0)),
+            _ => Err(MemoryOutOfBoundsError.into()),
+        }
+    }
+}
@@ -1,5 +1,15 @@
 pub use anyhow::{anyhow as err_str, Result};
+pub mod log;
+pub mod rng;
 pub mod spec;
+pub mod wasi;
+
+/// Pure rust backend, also usable natively to compare with the default one
+#[cfg(feature = "interpreter")]
+pub mod interpreter;
+/// Scripted backend to test without wasm
+#[cfg(feature = "mock")]
+pub mod mock;
 
 #[cfg_attr(target_arch = "wasm32", path = "wasm/mod.rs")]
 #[cfg_attr(not(target_arch = "wasm32"), path = "other/mod.rs")]
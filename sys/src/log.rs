@@ -0,0 +1,155 @@
+//! Bounded buffer of structured program logs
+
+use std::collections::VecDeque;
+
+/// Default byte capacity of a [`LogBuffer`]
+pub const DEFAULT_CAP: usize = 4096;
+/// Bytes counted for each record and field besides its text, bounds empty ones
+pub const OVERHEAD: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+impl Level {
+    pub fn from_i32(v: i32) -> Option<Self> {
+        Some(match v {
+            0 => Self::Debug,
+            1 => Self::Info,
+            2 => Self::Warn,
+            3 => Self::Error,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Record {
+    pub level: Level,
+    pub msg: String,
+    pub fields: Vec<(String, String)>,
+}
+impl Record {
+    pub fn new(level: Level, msg: String) -> Self {
+        Self {
+            level,
+            msg,
+            fields: Vec::new(),
+        }
+    }
+    fn size(&self) -> usize {
+        OVERHEAD
+            + self.msg.len()
+            + self
+                .fields
+                .iter()
+                .map(|(k, v)| OVERHEAD + k.len() + v.len())
+                .sum::<usize>()
+    }
+}
+
+/// Ring buffer of records, oldest ones are dropped once over capacity
+pub struct LogBuffer {
+    records: VecDeque<Record>,
+    size: usize,
+    cap: usize,
+    dropped: usize,
+    /// Unfinished lines of stdout and stderr like streams
+    lines: [(Level, Vec<u8>); 2],
+}
+impl LogBuffer {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            records: VecDeque::new(),
+            size: 0,
+            cap,
+            dropped: 0,
+            lines: [(Level::Info, Vec::new()), (Level::Error, Vec::new())],
+        }
+    }
+
+    pub fn push(&mut self, mut record: Record) {
+        if record.size() > self.cap {
+            record.fields.clear();
+            let mut end = self.cap.saturating_sub(OVERHEAD).min(record.msg.len());
+            while !record.msg.is_char_boundary(end) {
+                end -= 1;
+            }
+            record.msg.truncate(end);
+        }
+        self.size += record.size();
+        self.records.push_back(record);
+        while self.size > self.cap {
+            let Some(old) = self.records.pop_front() else {
+                break;
+            };
+            self.size -= old.size();
+            self.dropped += 1;
+        }
+    }
+    /// Append to a stream, each line is a record
+    pub fn write(&mut self, level: Level, v: &[u8]) {
+        let i = match level {
+            Level::Error | Level::Warn => 1,
+            Level::Debug | Level::Info => 0,
+        };
+        let lines = v.iter().filter(|b| **b == b'\n').count();
+        // Each record is at least OVERHEAD, so older lines would be dropped anyway
+        let skip = lines.saturating_sub(self.cap / OVERHEAD + 1);
+        let mut parts = v.split(|b| *b == b'\n');
+        for (k, part) in parts.by_ref().take(lines).enumerate() {
+            if k < skip {
+                self.lines[i].1.clear();
+                self.dropped += 1;
+            } else if k == 0 && !self.lines[i].1.is_empty() {
+                let mut line = std::mem::take(&mut self.lines[i].1);
+                line.extend_from_slice(part);
+                self.push_line(i, &line);
+            } else {
+                self.push_line(i, part);
+            }
+        }
+        let rest = parts.next().unwrap_or_default();
+        let pending = &mut self.lines[i].1;
+        // Overlong lines are truncated when pushed
+        let room = (self.cap + 1).saturating_sub(pending.len());
+        pending.extend_from_slice(&rest[..rest.len().min(room)]);
+        if pending.len() > self.cap {
+            let line = std::mem::take(pending);
+            self.push_line(i, &line);
+        }
+    }
+    fn push_line(&mut self, i: usize, line: &[u8]) {
+        let msg = String::from_utf8_lossy(line).into_owned();
+        self.push(Record::new(self.lines[i].0, msg));
+    }
+
+    /// Take all records, with a warning first if some were dropped
+    pub fn drain(&mut self) -> Vec<Record> {
+        for i in 0..self.lines.len() {
+            if !self.lines[i].1.is_empty() {
+                let line = std::mem::take(&mut self.lines[i].1);
+                self.push_line(i, &line);
+            }
+        }
+        let mut out = Vec::with_capacity(self.records.len() + 1);
+        if self.dropped > 0 {
+            out.push(Record::new(
+                Level::Warn,
+                format!("{} messages dropped", self.dropped),
+            ));
+            self.dropped = 0;
+        }
+        out.extend(self.records.drain(..));
+        self.size = 0;
+        out
+    }
+}
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAP)
+    }
+}
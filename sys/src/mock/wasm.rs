@@ -0,0 +1,317 @@
+//! Fake backend running scripted host calls instead of wasm
+//!
+//! Module code is text with one instruction per line:
+//! - `<export>` declares an empty `() -> ()` function
+//! - `<export> <module>.<name> [args...]` calls an import
+//! - `<export> trap [message]` aborts the call
+//! - `data <offset> <text>` initializes memory
+//!
+//! Each executed instruction consumes one fuel.
+pub use crate::spec::wasm as spec;
+use anyhow::{anyhow, bail, Context, Result};
+use spec::Value;
+use std::{collections::HashMap, sync::Arc};
+
+/// Memory size of every instance
+pub const MEMORY_SIZE: usize = 1 << 16;
+
+#[derive(Default)]
+pub struct Engine;
+impl Engine {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[repr(transparent)]
+pub struct RawStore<T>(T);
+impl<T> spec::Store for RawStore<T> {
+    type T = T;
+
+    #[inline]
+    fn new(state: T) -> Self {
+        Self(state)
+    }
+    #[inline]
+    fn state(&self) -> &T {
+        &self.0
+    }
+    #[inline]
+    fn state_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+pub use crate::wasi::{WasiProfile, WasiStore};
+
+type HostFunc<S> = Arc<spec::DynHostFunc<S>>;
+
+pub struct Linker<S> {
+    funcs: HashMap<(String, String), (spec::Signature, HostFunc<S>)>,
+    exports: Vec<spec::LinkExport>,
+    imports: spec::Imports,
+}
+impl<S: spec::Store> spec::Linker<S> for Linker<S> {
+    type Engine = Engine;
+    type Template = Template<S>;
+
+    fn new(_: &Engine) -> Self {
+        Self {
+            funcs: HashMap::new(),
+            exports: Vec::new(),
+            imports: spec::Imports::default(),
+        }
+    }
+    fn add_dyn_func(
+        &mut self,
+        module: &str,
+        name: &str,
+        sig: spec::Signature,
+        func: Box<spec::DynHostFunc<S>>,
+    ) -> Result<&mut Self> {
+        let key = (module.to_owned(), name.to_owned());
+        if self.funcs.contains_key(&key) {
+            bail!("import of `{}::{}` defined twice", module, name)
+        }
+        self.imports.define(module, name, sig.clone());
+        self.funcs.insert(key, (sig, func.into()));
+        Ok(self)
+    }
+    #[inline]
+    fn add_export(&mut self, v: spec::LinkExport) -> &mut Self {
+        self.exports.push(v);
+        self
+    }
+    #[inline]
+    fn imports(&self) -> &spec::Imports {
+        &self.imports
+    }
+
+    #[inline]
+    fn link(&self, bytes: &[u8]) -> Result<Template<S>> {
+        Template::new(self, bytes)
+    }
+}
+impl<T: 'static> Linker<WasiStore<T>> {
+    pub fn add_wasi(&mut self) -> &mut Self {
+        crate::wasi::add_to_linker(self).unwrap();
+        self.imports.unversioned(crate::wasi::MODULE);
+        self
+    }
+}
+
+enum Op<S> {
+    Call {
+        func: HostFunc<S>,
+        params: Vec<Value>,
+        results: usize,
+    },
+    Trap(String),
+}
+
+/// Parsed script validated with linker
+pub struct Template<S> {
+    exports: Arc<HashMap<String, Vec<Op<S>>>>,
+    data: Vec<(usize, Vec<u8>)>,
+}
+impl<S: spec::Store> Template<S> {
+    fn new(linker: &Linker<S>, bytes: &[u8]) -> Result<Self> {
+        let code = std::str::from_utf8(bytes).context("mock module is not text")?;
+        let mut exports: HashMap<String, Vec<Op<S>>> = HashMap::new();
+        let mut data = Vec::new();
+        let mut issues = Vec::new();
+        let mut modules = Vec::new();
+        for (i, line) in code.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (head, tail) = line.split_once(' ').unwrap_or((line, ""));
+            if head == "data" {
+                let (offset, text) = tail.split_once(' ').unwrap_or((tail, ""));
+                let offset: usize = offset.parse().with_context(|| format!("line {}", i + 1))?;
+                if offset + text.len() > MEMORY_SIZE {
+                    bail!("line {}: data out of memory", i + 1)
+                }
+                data.push((offset, text.as_bytes().to_vec()));
+                continue;
+            }
+            let ops = exports.entry(head.to_owned()).or_default();
+            let mut args = tail.split_whitespace();
+            let Some(target) = args.next() else {
+                continue;
+            };
+            if target == "trap" {
+                ops.push(Op::Trap(args.collect::<Vec<_>>().join(" ")));
+                continue;
+            }
+            let Some((module, name)) = target.split_once('.') else {
+                bail!("line {}: expected `module.name`", i + 1)
+            };
+            modules.push(module);
+            let Some((sig, func)) = linker.funcs.get(&(module.to_owned(), name.to_owned())) else {
+                issues.push(spec::LinkIssue::UnknownImport {
+                    module: module.to_owned(),
+                    name: name.to_owned(),
+                });
+                continue;
+            };
+            let args: Vec<i64> = args
+                .map(|a| a.parse())
+                .collect::<Result<_, _>>()
+                .with_context(|| format!("line {}", i + 1))?;
+            if args.len() != sig.params.len() {
+                bail!("incompatible import type for `{}::{}`", module, name)
+            }
+            let params = sig
+                .params
+                .iter()
+                .zip(args)
+                .map(|(ty, v)| match ty {
+                    spec::ValType::I32 => Value::I32(v as i32),
+                    spec::ValType::I64 => Value::I64(v),
+                })
+                .collect();
+            ops.push(Op::Call {
+                func: func.clone(),
+                params,
+                results: sig.results.len(),
+            });
+        }
+        issues.extend(linker.imports.check_versions(modules));
+        issues.extend(spec::check_exports(&linker.exports, |name| {
+            exports
+                .contains_key(name)
+                .then(|| Some(spec::Signature::default()))
+        }));
+        spec::LinkError::check(issues)?;
+        Ok(Self {
+            exports: Arc::new(exports),
+            data,
+        })
+    }
+}
+
+pub struct Instance<S> {
+    exports: Arc<HashMap<String, Vec<Op<S>>>>,
+    memory: Vec<u8>,
+    fuel: u64,
+    store: S,
+}
+impl<S: spec::Store> spec::Instance<S> for Instance<S> {
+    type Template = Template<S>;
+    type Func = String;
+
+    fn new(tpl: &Template<S>, data: S::T, fuel: u64) -> Result<Self> {
+        let mut memory = vec![0; MEMORY_SIZE];
+        for (offset, bytes) in tpl.data.iter() {
+            memory[*offset..*offset + bytes.len()].copy_from_slice(bytes);
+        }
+        Ok(Self {
+            exports: tpl.exports.clone(),
+            memory,
+            fuel,
+            store: S::new(data),
+        })
+    }
+
+    #[inline]
+    fn store(&self) -> &S {
+        &self.store
+    }
+    #[inline]
+    fn store_mut(&mut self) -> &mut S {
+        &mut self.store
+    }
+
+    fn get_dyn_func(&mut self, name: &str, sig: &spec::Signature) -> Result<String> {
+        if !self.exports.contains_key(name) {
+            bail!("Missing '{}' export", name)
+        }
+        if *sig != spec::Signature::default() {
+            bail!("'{}' function signature is {}", name, sig)
+        }
+        Ok(name.to_owned())
+    }
+    fn call_dyn(&mut self, f: &String, _: &[Value], _: &mut [Value]) -> Result<()> {
+        let exports = self.exports.clone();
+        let Some(ops) = exports.get(f) else {
+            bail!("Missing '{}' export", f)
+        };
+        for op in ops {
+            spec::Instance::consume_fuel(self, 1)?;
+            match op {
+                Op::Call {
+                    func,
+                    params,
+                    results,
+                } => {
+                    let mut results = vec![Value::I64(0); *results];
+                    func(
+                        &mut HostCaller {
+                            store: &mut self.store,
+                            memory: &mut self.memory,
+                            fuel: &mut self.fuel,
+                        },
+                        params,
+                        &mut results,
+                    )?;
+                }
+                Op::Trap(msg) => bail!("wasm trap: {}", msg),
+            }
+        }
+        Ok(())
+    }
+
+    fn add_fuel(&mut self, v: u64) -> Result<(), std::num::TryFromIntError> {
+        let fuel = self.fuel.saturating_add(v);
+        i64::try_from(fuel)?;
+        self.fuel = fuel;
+        Ok(())
+    }
+    #[inline]
+    fn consume_fuel(&mut self, v: u64) -> Result<u64> {
+        consume(&mut self.fuel, v)
+    }
+}
+
+#[inline]
+fn consume(fuel: &mut u64, v: u64) -> Result<u64> {
+    *fuel = fuel
+        .checked_sub(v)
+        .ok_or_else(|| anyhow!("all fuel consumed"))?;
+    Ok(*fuel)
+}
+
+pub use anyhow::Error;
+pub use spec::MemoryOutOfBoundsError;
+pub type Func<P, R> = spec::TypedFunc<String, P, R>;
+
+/// Message and frames of a trap, backtraces are not available
+pub fn split_backtrace(err: &Error) -> (String, Vec<spec::Frame>) {
+    (format!("{:#}", err), Vec::new())
+}
+
+struct HostCaller<'a, S> {
+    store: &'a mut S,
+    memory: &'a mut [u8],
+    fuel: &'a mut u64,
+}
+impl<S> spec::Caller<S> for HostCaller<'_, S> {
+    #[inline]
+    fn store(&self) -> &S {
+        self.store
+    }
+    #[inline]
+    fn store_mut(&mut self) -> &mut S {
+        self.store
+    }
+    #[inline]
+    fn consume_fuel(&mut self, v: u64) -> Result<u64> {
+        consume(self.fuel, v)
+    }
+    #[inline]
+    fn memory(&mut self) -> Result<(&mut [u8], &mut S)> {
+        Ok((self.memory, self.store))
+    }
+}
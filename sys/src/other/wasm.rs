@@ -1,14 +1,11 @@
 pub use crate::spec::wasm as spec;
 use anyhow::{bail, Result};
-use std::{
-    io::{self, Write},
-    sync::{Arc, RwLock},
-};
-use wasi_common::pipe::WritePipe;
-use wasmtime_wasi::{sync::WasiCtxBuilder, WasiCtx};
+use sha2::{Digest, Sha256};
+use spec::Value;
+use std::sync::{atomic, Arc};
+use std::{fs, path::PathBuf};
 
-#[repr(transparent)]
-pub struct Engine(wasmtime::Engine);
+pub struct Engine(wasmtime::Engine, Option<Arc<ModuleCache>>);
 impl Engine {
     pub fn new() -> Self {
         let mut config = wasmtime::Config::new();
@@ -20,8 +17,74 @@ impl Engine {
                 _ = config.cache_config_load_default();
             }
         }
-        config.consume_fuel(true);
-        Self(wasmtime::Engine::new(&config).unwrap())
+        config
+            .consume_fuel(true)
+            .wasm_backtrace_details(wasmtime::WasmBacktraceDetails::Enable);
+        let engine = wasmtime::Engine::new(&config).unwrap();
+        let cache = std::env::var("MODULE_CACHE_DIR").ok().and_then(|dir| {
+            ModuleCache::new(dir, &engine)
+                .map_err(|err| tracing::error!("module cache disabled: {:#}", err))
+                .ok()
+                .map(Arc::new)
+        });
+        Self(engine, cache)
+    }
+}
+
+/// Precompiled modules stored on disk by code hash
+///
+/// Deserialized modules are trusted, so the directory must not be writable by others
+pub struct ModuleCache {
+    dir: PathBuf,
+    /// Hasher fed with engine config
+    config: Sha256,
+}
+impl ModuleCache {
+    pub fn new(dir: impl Into<PathBuf>, engine: &wasmtime::Engine) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        // Embeds wasmtime version, target and settings like fuel metering
+        let empty = engine.precompile_module(b"(module)")?;
+        let config = Sha256::new_with_prefix(empty);
+        Ok(Self { dir, config })
+    }
+
+    fn path(&self, bytes: &[u8]) -> PathBuf {
+        let mut hasher = self.config.clone();
+        hasher.update(bytes);
+        let name: String = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        self.dir.join(name)
+    }
+
+    /// Load precompiled module or compile and store it
+    pub fn get_or_compile(
+        &self,
+        engine: &wasmtime::Engine,
+        bytes: &[u8],
+    ) -> Result<wasmtime::Module> {
+        let path = self.path(bytes);
+        if let Ok(data) = fs::read(&path) {
+            // SAFETY: cache directory is trusted
+            if let Ok(module) = unsafe { wasmtime::Module::deserialize(engine, data) } {
+                return Ok(module);
+            }
+        }
+        let module = wasmtime::Module::new(engine, bytes)?;
+        _ = self.store(&path, &module);
+        Ok(module)
+    }
+    fn store(&self, path: &PathBuf, module: &wasmtime::Module) -> Result<()> {
+        // Unique among workers and processes sharing the directory
+        static NEXT: atomic::AtomicU64 = atomic::AtomicU64::new(0);
+        let n = NEXT.fetch_add(1, atomic::Ordering::Relaxed);
+        let tmp = path.with_extension(format!("{}-{}.tmp", std::process::id(), n));
+        fs::write(&tmp, module.serialize()?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
     }
 }
 
@@ -44,213 +107,291 @@ impl<T> spec::Store for RawStore<T> {
     }
 }
 
-pub struct WasiStore<T> {
-    //MAYBE: bounded Vec
-    log: Arc<RwLock<io::Cursor<Vec<u8>>>>,
-    wasi: WasiCtx,
-    state: T,
-}
-impl<T> spec::Store for WasiStore<T> {
-    type T = T;
+pub use crate::wasi::{WasiProfile, WasiStore};
 
-    fn new(state: T) -> Self {
-        let log = Arc::new(RwLock::new(io::Cursor::new(vec![])));
-        let wasi = WasiCtxBuilder::new()
-            .stdout(Box::new(WritePipe::from_shared(log.clone())))
-            .stderr(Box::new(WritePipe::from_shared(log.clone())))
-            .build();
-        Self { log, wasi, state }
-    }
-    #[inline]
-    fn state(&self) -> &T {
-        &self.state
-    }
-    #[inline]
-    fn state_mut(&mut self) -> &mut T {
-        &mut self.state
-    }
-}
-impl<T> WasiStore<T> {
-    #[inline]
-    fn get_wasi(&mut self) -> &mut WasiCtx {
-        &mut self.wasi
-    }
-
-    pub fn read_log(&mut self) -> String {
-        let mut cursor = self.log.write().unwrap();
-        cursor.set_position(0);
-        let bytes = cursor.get_mut();
-        let s = String::from_utf8_lossy(bytes).into_owned();
-        bytes.clear();
-        s
-    }
-    pub fn write_log(&mut self, v: &[u8]) {
-        let mut cursor = self.log.write().unwrap();
-        cursor.write_all(v).unwrap()
-    }
-}
+pub struct Linker<S>(
+    wasmtime::Linker<S>,
+    Vec<spec::LinkExport>,
+    Option<Arc<ModuleCache>>,
+    spec::Imports,
+);
+impl<S: spec::Store + 'static> spec::Linker<S> for Linker<S> {
+    type Engine = Engine;
+    type Template = Template<S>;
 
-pub struct Linker<S>(wasmtime::Linker<S>, Vec<spec::LinkExport>);
-impl<S> Linker<S> {
-    pub fn new(engine: &Engine) -> Self {
-        Self(wasmtime::Linker::new(&engine.0), Vec::new())
+    fn new(engine: &Engine) -> Self {
+        Self(
+            wasmtime::Linker::new(&engine.0),
+            Vec::new(),
+            engine.1.clone(),
+            spec::Imports::default(),
+        )
     }
-    #[inline]
-    pub fn add_func<P, R>(
+    fn add_dyn_func(
         &mut self,
         module: &str,
         name: &str,
-        func: impl wasmtime::IntoFunc<S, P, R>,
+        sig: spec::Signature,
+        func: Box<spec::DynHostFunc<S>>,
     ) -> Result<&mut Self> {
-        self.0.func_wrap(module, name, func)?;
+        if sig.params.len() > spec::MAX_VALUES || sig.results.len() > spec::MAX_VALUES {
+            bail!("`{}::{}` signature {} is too long", module, name, sig)
+        }
+        let ty = wasmtime::FuncType::new(
+            sig.params.iter().map(|t| val_type(*t)),
+            sig.results.iter().map(|t| val_type(*t)),
+        );
+        self.3.define(module, name, sig.clone());
+        // SAFETY: raw values are read and written as declared by `ty`
+        unsafe {
+            self.0
+                .func_new_unchecked(module, name, ty, move |caller, raw| {
+                    let mut buf = [Value::I64(0); 2 * spec::MAX_VALUES];
+                    let (params, results) = buf.split_at_mut(spec::MAX_VALUES);
+                    let params = &mut params[..sig.params.len()];
+                    for ((slot, ty), v) in params.iter_mut().zip(&sig.params).zip(raw.iter()) {
+                        *slot = from_raw(*ty, v);
+                    }
+                    let results = &mut results[..sig.results.len()];
+                    for (slot, ty) in results.iter_mut().zip(&sig.results) {
+                        *slot = Value::default_of(*ty);
+                    }
+                    func(&mut HostCaller(caller), params, results)?;
+                    for (v, slot) in results.iter().zip(raw.iter_mut()) {
+                        *slot = into_raw(*v);
+                    }
+                    Ok(())
+                })?;
+        }
         Ok(self)
     }
     #[inline]
-    pub fn add_export(&mut self, v: spec::LinkExport) -> &mut Self {
+    fn add_export(&mut self, v: spec::LinkExport) -> &mut Self {
         self.1.push(v);
         self
     }
+    #[inline]
+    fn imports(&self) -> &spec::Imports {
+        &self.3
+    }
 
     #[inline]
-    pub fn link(&mut self, bytes: impl AsRef<[u8]>) -> Result<Template<S>>
-    where
-        S: spec::Store,
-    {
+    fn link(&self, bytes: &[u8]) -> Result<Template<S>> {
         Template::new(self, bytes)
     }
 }
 impl<T: 'static> Linker<WasiStore<T>> {
     pub fn add_wasi(&mut self) -> &mut Self {
-        wasmtime_wasi::add_to_linker(&mut self.0, WasiStore::get_wasi).unwrap();
+        crate::wasi::add_to_linker(self).unwrap();
+        self.3.unversioned(crate::wasi::MODULE);
         self
     }
 }
 
+#[inline]
+fn val_type(t: spec::ValType) -> wasmtime::ValType {
+    match t {
+        spec::ValType::I32 => wasmtime::ValType::I32,
+        spec::ValType::I64 => wasmtime::ValType::I64,
+    }
+}
+#[inline]
+fn signature(ty: &wasmtime::FuncType) -> Option<spec::Signature> {
+    fn types(it: impl Iterator<Item = wasmtime::ValType>) -> Option<Vec<spec::ValType>> {
+        it.map(|t| match t {
+            wasmtime::ValType::I32 => Some(spec::ValType::I32),
+            wasmtime::ValType::I64 => Some(spec::ValType::I64),
+            _ => None,
+        })
+        .collect()
+    }
+    Some(spec::Signature {
+        params: types(ty.params())?,
+        results: types(ty.results())?,
+    })
+}
+#[inline]
+fn from_val(v: &wasmtime::Val) -> Result<Value> {
+    match v {
+        wasmtime::Val::I32(v) => Ok(Value::I32(*v)),
+        wasmtime::Val::I64(v) => Ok(Value::I64(*v)),
+        _ => bail!("Unsupported value type {:?}", v.ty()),
+    }
+}
+#[inline]
+unsafe fn from_raw(t: spec::ValType, v: &wasmtime::ValRaw) -> Value {
+    match t {
+        spec::ValType::I32 => Value::I32(v.get_i32()),
+        spec::ValType::I64 => Value::I64(v.get_i64()),
+    }
+}
+#[inline]
+fn into_raw(v: Value) -> wasmtime::ValRaw {
+    match v {
+        Value::I32(v) => wasmtime::ValRaw::i32(v),
+        Value::I64(v) => wasmtime::ValRaw::i64(v),
+    }
+}
+#[inline]
+fn into_val(v: Value) -> wasmtime::Val {
+    match v {
+        Value::I32(v) => wasmtime::Val::I32(v),
+        Value::I64(v) => wasmtime::Val::I64(v),
+    }
+}
+
 /// Module validated with linker
 #[repr(transparent)]
 pub struct Template<S>(wasmtime::InstancePre<S>);
 impl<S: spec::Store> Template<S> {
-    pub fn new(linker: &Linker<S>, bytes: impl AsRef<[u8]>) -> Result<Self> {
-        let module = wasmtime::Module::new(linker.0.engine(), bytes)?;
+    fn new(linker: &Linker<S>, bytes: &[u8]) -> Result<Self> {
+        let module = match &linker.2 {
+            Some(cache) => cache.get_or_compile(linker.0.engine(), bytes)?,
+            None => wasmtime::Module::new(linker.0.engine(), bytes)?,
+        };
+        let mut issues: Vec<_> = module
+            .imports()
+            .filter_map(|import| {
+                let found = match import.ty() {
+                    wasmtime::ExternType::Func(ty) => signature(&ty),
+                    _ => None,
+                };
+                linker.3.check(import.module(), import.name(), found)
+            })
+            .collect();
+        issues.extend(
+            linker
+                .3
+                .check_versions(module.imports().map(|import| import.module())),
+        );
+        issues.extend(spec::check_exports(&linker.1, |name| {
+            module.get_export(name).map(|ex| match ex {
+                wasmtime::ExternType::Func(ty) => signature(&ty),
+                _ => None,
+            })
+        }));
+        spec::LinkError::check(issues)?;
         let inner = linker.0.instantiate_pre(&module)?;
-        for export in linker.1.iter() {
-            _ = Self::has_export(inner.module(), export)?;
-        }
         Ok(Self(inner))
     }
-    #[inline]
-    fn has_export(module: &wasmtime::Module, export: &spec::LinkExport) -> Result<bool> {
-        if let Some(ex) = module.get_export(export.name) {
-            match export.value {
-                spec::ExportType::UnitFunc => match ex {
-                    wasmtime::ExternType::Func(typ) => {
-                        if typ.params().len() == 0 || typ.results().len() == 0 {
-                            Ok(true)
-                        } else {
-                            bail!("'{}' function signature is () -> ()", export.name)
-                        }
-                    }
-                    _ => {
-                        bail!("'{}' export is not a function", export.name)
-                    }
-                },
-            }
-        } else if export.required {
-            bail!("Missing '{}' export", export.name)
-        } else {
-            Ok(false)
-        }
-    }
 }
 
-pub trait IntoStore {}
+/// Exported function, with a typed fast path for `() -> ()`
+#[derive(Clone)]
+pub struct Export(wasmtime::Func, Option<wasmtime::TypedFunc<(), ()>>);
+
 pub struct Instance<S>(wasmtime::Instance, wasmtime::Store<S>);
-impl<S: spec::Store> Instance<S> {
-    pub fn new(tpl: &Template<S>, data: S::T, fuel: u64) -> Self {
+impl<S: spec::Store> spec::Instance<S> for Instance<S> {
+    type Template = Template<S>;
+    type Func = Export;
+
+    fn new(tpl: &Template<S>, data: S::T, fuel: u64) -> Result<Self> {
         let mut store = wasmtime::Store::new(tpl.0.module().engine(), S::new(data));
-        store.add_fuel(fuel).unwrap();
-        let i = tpl.0.instantiate(&mut store).unwrap();
-        Self(i, store)
-    }
-    pub fn started(tpl: &Template<S>, data: S::T, fuel: u64) -> (Self, Result<()>) {
-        let mut i = Self::new(tpl, data, fuel);
-        let res = if let Ok(start) = i.get_func(spec::MAY_EXPORT_START.name) {
-            i.call(&start, ())
-        } else {
-            Ok(())
-        };
-        (i, res)
+        store.add_fuel(fuel)?;
+        let i = tpl.0.instantiate(&mut store)?;
+        Ok(Self(i, store))
     }
 
     #[inline]
-    pub fn get_func<P, R>(&mut self, name: &str) -> Result<Func<P, R>>
-    where
-        P: wasmtime::WasmParams,
-        R: wasmtime::WasmResults,
-    {
-        self.0.get_typed_func(&mut self.1, name)
+    fn store(&self) -> &S {
+        self.1.data()
+    }
+    #[inline]
+    fn store_mut(&mut self) -> &mut S {
+        self.1.data_mut()
     }
 
-    pub fn fuel(&mut self) -> u64 {
-        self.consume_fuel(0).unwrap_or(0)
+    fn get_dyn_func(&mut self, name: &str, sig: &spec::Signature) -> Result<Export> {
+        let Some(f) = self.0.get_func(&mut self.1, name) else {
+            bail!("Missing '{}' export", name)
+        };
+        if signature(&f.ty(&self.1)).as_ref() != Some(sig) {
+            bail!("'{}' function signature is {}", name, sig)
+        }
+        Ok(Export(f, f.typed(&self.1).ok()))
+    }
+    #[inline]
+    fn call_dyn(&mut self, f: &Export, params: &[Value], results: &mut [Value]) -> Result<()> {
+        if let Some(unit) = &f.1 {
+            return unit.call(&mut self.1, ());
+        }
+        let params: Vec<_> = params.iter().copied().map(into_val).collect();
+        let mut vals: Vec<_> = results.iter().copied().map(into_val).collect();
+        f.0.call(&mut self.1, &params, &mut vals)?;
+        for (slot, v) in results.iter_mut().zip(vals.iter()) {
+            *slot = from_val(v)?;
+        }
+        Ok(())
     }
-    pub fn add_fuel(&mut self, v: u64) -> Result<(), std::num::TryFromIntError> {
+
+    fn add_fuel(&mut self, v: u64) -> Result<(), std::num::TryFromIntError> {
         i64::try_from(self.fuel().checked_add(v).unwrap_or(u64::MAX))?;
         self.1.add_fuel(v).unwrap();
         Ok(())
     }
     #[inline]
-    pub fn consume_fuel(&mut self, v: u64) -> Result<u64> {
+    fn consume_fuel(&mut self, v: u64) -> Result<u64> {
         self.1.consume_fuel(v)
     }
 }
-impl<'a, S> spec::StoreRef<'a, S> for Instance<S>
-where
-    S: spec::Store + 'a,
-{
-    #[inline]
-    fn store(&self) -> &S {
-        self.1.data()
-    }
-    #[inline]
-    fn store_mut(&mut self) -> &mut S {
-        self.1.data_mut()
-    }
-}
 
 pub use anyhow::Error;
-pub use wasmtime::{Caller, Trap::MemoryOutOfBounds as MemoryOutOfBoundsError, TypedFunc as Func};
-impl<S> Instance<S> {
-    #[inline]
-    pub fn call<P, R>(&mut self, f: &Func<P, R>, p: P) -> Result<R>
-    where
-        P: wasmtime::WasmParams,
-        R: wasmtime::WasmResults,
-    {
-        f.call(&mut self.1, p)
+pub use spec::MemoryOutOfBoundsError;
+pub type Func<P, R> = spec::TypedFunc<Export, P, R>;
+
+/// Message without backtrace and symbolicated frames of a trap
+pub fn split_backtrace(err: &Error) -> (String, Vec<spec::Frame>) {
+    let Some(bt) = err.downcast_ref::<wasmtime::WasmBacktrace>() else {
+        return (format!("{:#}", err), Vec::new());
+    };
+    let bt_msg = bt.to_string();
+    let msg: Vec<_> = err
+        .chain()
+        .map(|e| e.to_string())
+        .filter(|e| *e != bt_msg)
+        .collect();
+    let mut frames = Vec::new();
+    for frame in bt.frames() {
+        let func = frame.func_index();
+        if frame.symbols().is_empty() {
+            frames.push(spec::Frame {
+                func,
+                name: frame.func_name().map(spec::demangle),
+                ..Default::default()
+            });
+        }
+        // Inlined functions are multiple symbols of a frame
+        for symbol in frame.symbols() {
+            frames.push(spec::Frame {
+                func,
+                name: (symbol.name().or(frame.func_name())).map(spec::demangle),
+                file: symbol.file().map(str::to_owned),
+                line: symbol.line(),
+                column: symbol.column(),
+            });
+        }
     }
+    (msg.join(": "), frames)
 }
 
-impl<'a, S> spec::StoreRef<'a, S> for Caller<'_, S>
-where
-    S: spec::Store + 'a,
-{
+#[repr(transparent)]
+struct HostCaller<'a, S>(wasmtime::Caller<'a, S>);
+impl<S> spec::Caller<S> for HostCaller<'_, S> {
     #[inline]
     fn store(&self) -> &S {
-        self.data()
+        self.0.data()
     }
     #[inline]
     fn store_mut(&mut self) -> &mut S {
-        self.data_mut()
+        self.0.data_mut()
     }
-}
-pub trait CallerMemoryExt {
-    fn memory(&mut self) -> Result<wasmtime::Memory>;
-}
-impl<S> CallerMemoryExt for Caller<'_, S> {
-    fn memory(&mut self) -> Result<wasmtime::Memory> {
-        match self.get_export("memory") {
-            Some(wasmtime::Extern::Memory(m)) => Ok(m),
+    #[inline]
+    fn consume_fuel(&mut self, v: u64) -> Result<u64> {
+        self.0.consume_fuel(v)
+    }
+    fn memory(&mut self) -> Result<(&mut [u8], &mut S)> {
+        match self.0.get_export("memory") {
+            Some(wasmtime::Extern::Memory(m)) => Ok(m.data_and_store_mut(&mut self.0)),
             _ => Err(MemoryOutOfBoundsError.into()),
         }
     }
@@ -1,24 +1,18 @@
+//! Backend independent wasm runtime interface
+
+use anyhow::{anyhow, Result};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    marker::PhantomData,
+};
+
 pub trait Store {
     type T;
     fn new(state: Self::T) -> Self;
     fn state(&self) -> &Self::T;
     fn state_mut(&mut self) -> &mut Self::T;
 }
-pub trait StoreRef<'a, S>
-where
-    S: Store + 'a,
-{
-    fn store(&self) -> &S;
-    fn store_mut(&mut self) -> &mut S;
-    #[inline]
-    fn state(&'a self) -> &'a S::T {
-        self.store().state()
-    }
-    #[inline]
-    fn state_mut(&'a mut self) -> &'a mut S::T {
-        self.store_mut().state_mut()
-    }
-}
 
 #[derive(Clone)]
 pub struct LinkExport {
@@ -29,9 +23,633 @@ pub struct LinkExport {
 #[derive(Clone)]
 pub enum ExportType {
     UnitFunc,
+    Func {
+        params: &'static [ValType],
+        results: &'static [ValType],
+    },
+}
+impl ExportType {
+    pub fn signature(&self) -> Signature {
+        match self {
+            ExportType::UnitFunc => Signature::default(),
+            ExportType::Func { params, results } => Signature {
+                params: params.to_vec(),
+                results: results.to_vec(),
+            },
+        }
+    }
+}
+impl LinkExport {
+    /// Check module export given its signature or `None` if not a function
+    pub fn validate(&self, found: Option<Option<Signature>>) -> Option<LinkIssue> {
+        let expected = self.value.signature();
+        match found {
+            Some(Some(sig)) if sig == expected => None,
+            Some(found) => Some(LinkIssue::ExportSignature {
+                name: self.name,
+                expected,
+                found,
+            }),
+            None if self.required => Some(LinkIssue::MissingExport { name: self.name }),
+            None => None,
+        }
+    }
 }
 pub static MAY_EXPORT_START: LinkExport = LinkExport {
     name: "_start",
     required: false,
     value: ExportType::UnitFunc,
 };
+
+/// Check all exports, `get` returns the signature of an export or `None` if not a function
+pub fn check_exports(
+    exports: &[LinkExport],
+    mut get: impl FnMut(&str) -> Option<Option<Signature>>,
+) -> Vec<LinkIssue> {
+    exports
+        .iter()
+        .filter_map(|ex| ex.validate(get(ex.name)))
+        .collect()
+}
+
+/// Import module name for an api version, `0` is unversioned
+pub fn versioned(module: &str, version: u32) -> String {
+    match version {
+        0 => module.to_owned(),
+        v => format!("{}@{}", module, v),
+    }
+}
+/// Split import module name like `motor@1` into name and api version
+pub fn split_version(module: &str) -> (&str, u32) {
+    module
+        .rsplit_once('@')
+        .and_then(|(name, v)| Some((name, v.parse().ok()?)))
+        .unwrap_or((module, 0))
+}
+/// Check that all import modules target the same api version
+pub fn check_versions<'a>(modules: impl IntoIterator<Item = &'a str>) -> Option<LinkIssue> {
+    let mut versions: Vec<u32> = modules.into_iter().map(|m| split_version(m).1).collect();
+    versions.sort_unstable();
+    versions.dedup();
+    (versions.len() > 1).then_some(LinkIssue::MixedVersions { versions })
+}
+
+/// Signatures of host functions by import name
+#[derive(Default)]
+pub struct Imports {
+    funcs: HashMap<(String, String), Signature>,
+    /// Modules validated by backend itself
+    external: HashSet<String>,
+    /// Modules outside of the versioned api
+    unversioned: HashSet<String>,
+}
+impl Imports {
+    /// Returns `false` if already defined
+    pub fn define(&mut self, module: &str, name: &str, sig: Signature) -> bool {
+        self.funcs
+            .insert((module.to_owned(), name.to_owned()), sig)
+            .is_none()
+    }
+    pub fn external(&mut self, module: &str) {
+        self.external.insert(module.to_owned());
+    }
+    /// Exclude a system module like wasi from version checks
+    pub fn unversioned(&mut self, module: &str) {
+        self.unversioned.insert(module.to_owned());
+    }
+    /// Whether a module is part of the versioned api
+    pub fn is_versioned(&self, module: &str) -> bool {
+        !self.external.contains(module) && !self.unversioned.contains(module)
+    }
+    /// Defined functions as `(module, name, signature)` in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str, &Signature)> {
+        self.funcs
+            .iter()
+            .map(|((module, name), sig)| (module.as_str(), name.as_str(), sig))
+    }
+    #[inline]
+    pub fn get(&self, module: &str, name: &str) -> Option<&Signature> {
+        self.funcs.get(&(module.to_owned(), name.to_owned()))
+    }
+    /// Check an import given its signature or `None` if not a function
+    pub fn check(&self, module: &str, name: &str, found: Option<Signature>) -> Option<LinkIssue> {
+        if self.external.contains(module) {
+            return None;
+        }
+        match self.get(module, name) {
+            None => Some(LinkIssue::UnknownImport {
+                module: module.to_owned(),
+                name: name.to_owned(),
+            }),
+            Some(expected) if found.as_ref() == Some(expected) => None,
+            Some(expected) => Some(LinkIssue::ImportSignature {
+                module: module.to_owned(),
+                name: name.to_owned(),
+                expected: expected.clone(),
+                found,
+            }),
+        }
+    }
+    /// Check api versions of imported modules excluding external ones
+    pub fn check_versions<'a>(
+        &self,
+        modules: impl IntoIterator<Item = &'a str>,
+    ) -> Option<LinkIssue> {
+        check_versions(
+            modules
+                .into_iter()
+                .filter(|module| self.is_versioned(module)),
+        )
+    }
+}
+
+/// Reason for a module to be rejected by a linker
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkIssue {
+    /// Import not provided by the linker
+    UnknownImport {
+        module: String,
+        name: String,
+    },
+    ImportSignature {
+        module: String,
+        name: String,
+        expected: Signature,
+        /// `None` if not a function or with unsupported types
+        found: Option<Signature>,
+    },
+    /// Imports from different api versions
+    MixedVersions {
+        versions: Vec<u32>,
+    },
+    MissingExport {
+        name: &'static str,
+    },
+    ExportSignature {
+        name: &'static str,
+        expected: Signature,
+        /// `None` if not a function or with unsupported types
+        found: Option<Signature>,
+    },
+}
+impl fmt::Display for LinkIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Found<'a>(&'a Option<Signature>);
+        impl fmt::Display for Found<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self.0 {
+                    Some(sig) => sig.fmt(f),
+                    None => f.write_str("an incompatible type"),
+                }
+            }
+        }
+        match self {
+            LinkIssue::UnknownImport { module, name } => {
+                write!(f, "Unknown import '{}.{}'", module, name)
+            }
+            LinkIssue::ImportSignature {
+                module,
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Import '{}.{}' signature is {} but found {}",
+                module,
+                name,
+                expected,
+                Found(found)
+            ),
+            LinkIssue::MixedVersions { versions } => {
+                f.write_str("Imports mix api versions")?;
+                for (i, v) in versions.iter().enumerate() {
+                    f.write_str(if i > 0 { ", " } else { " " })?;
+                    write!(f, "{}", v)?;
+                }
+                Ok(())
+            }
+            LinkIssue::MissingExport { name } => write!(f, "Missing '{}' export", name),
+            LinkIssue::ExportSignature {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Export '{}' signature is {} but found {}",
+                name,
+                expected,
+                Found(found)
+            ),
+        }
+    }
+}
+/// Module rejected by linker with all found issues
+#[derive(Debug)]
+pub struct LinkError(pub Vec<LinkIssue>);
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, issue) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            issue.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for LinkError {}
+impl LinkError {
+    /// Fails if any issue
+    pub fn check(issues: Vec<LinkIssue>) -> Result<()> {
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(LinkError(issues).into())
+        }
+    }
+}
+
+/// Value type allowed between host and guest
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValType {
+    I32,
+    I64,
+}
+impl fmt::Display for ValType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ValType::I32 => "i32",
+            ValType::I64 => "i64",
+        })
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+}
+impl Value {
+    #[inline]
+    pub fn default_of(ty: ValType) -> Self {
+        match ty {
+            ValType::I32 => Value::I32(0),
+            ValType::I64 => Value::I64(0),
+        }
+    }
+}
+
+/// Function type
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Signature {
+    pub params: Vec<ValType>,
+    pub results: Vec<ValType>,
+}
+impl Signature {
+    pub fn of<P: WasmTypes, R: WasmTypes>() -> Self {
+        Self {
+            params: P::TYPES.to_vec(),
+            results: R::TYPES.to_vec(),
+        }
+    }
+}
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list(f: &mut fmt::Formatter<'_>, types: &[ValType]) -> fmt::Result {
+            f.write_str("(")?;
+            for (i, ty) in types.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                ty.fmt(f)?;
+            }
+            f.write_str(")")
+        }
+        list(f, &self.params)?;
+        f.write_str(" -> ")?;
+        list(f, &self.results)
+    }
+}
+
+/// Rust type matching a [`ValType`]
+pub trait WasmType: Sized {
+    const TYPE: ValType;
+    fn from_value(v: Value) -> Option<Self>;
+    fn into_value(self) -> Value;
+}
+macro_rules! wasm_type {
+    ($($t:ty: $v:ident as $r:ty),*) => {$(
+        impl WasmType for $t {
+            const TYPE: ValType = ValType::$v;
+            #[inline]
+            fn from_value(v: Value) -> Option<Self> {
+                match v {
+                    Value::$v(v) => Some(v as $t),
+                    #[allow(unreachable_patterns)]
+                    _ => None,
+                }
+            }
+            #[inline]
+            fn into_value(self) -> Value {
+                Value::$v(self as $r)
+            }
+        }
+    )*};
+}
+wasm_type!(i32: I32 as i32, u32: I32 as i32, i64: I64 as i64, u64: I64 as i64);
+
+/// Most params or results of a host function, bounds stack buffers of backends
+pub const MAX_VALUES: usize = 16;
+
+/// Sequence of [`WasmType`] used as params or results
+pub trait WasmTypes: Sized {
+    /// At most [`MAX_VALUES`]
+    const TYPES: &'static [ValType];
+    fn from_values(v: &[Value]) -> Option<Self>;
+    /// Write into `out` of length [`WasmTypes::TYPES`]
+    fn write_values(self, out: &mut [Value]);
+}
+macro_rules! wasm_types_single {
+    ($($t:ty)*) => {$(
+        impl WasmTypes for $t {
+            const TYPES: &'static [ValType] = &[<$t>::TYPE];
+            #[inline]
+            fn from_values(v: &[Value]) -> Option<Self> {
+                match v {
+                    [v] => <$t>::from_value(*v),
+                    _ => None,
+                }
+            }
+            #[inline]
+            fn write_values(self, out: &mut [Value]) {
+                out[0] = self.into_value();
+            }
+        }
+    )*};
+}
+wasm_types_single!(i32 u32 i64 u64);
+macro_rules! wasm_types_tuple {
+    ($($a:ident)*) => {
+        impl<$($a: WasmType,)*> WasmTypes for ($($a,)*) {
+            const TYPES: &'static [ValType] = &[$($a::TYPE),*];
+            #[inline]
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn from_values(v: &[Value]) -> Option<Self> {
+                let mut it = v.iter();
+                $(let $a = $a::from_value(*it.next()?)?;)*
+                if it.next().is_some() {
+                    return None;
+                }
+                Some(($($a,)*))
+            }
+            #[inline]
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn write_values(self, out: &mut [Value]) {
+                let ($($a,)*) = self;
+                let mut it = out.iter_mut();
+                $(*it.next().unwrap() = $a.into_value();)*
+            }
+        }
+    };
+}
+wasm_types_tuple!();
+wasm_types_tuple!(A1);
+wasm_types_tuple!(A1 A2);
+wasm_types_tuple!(A1 A2 A3);
+wasm_types_tuple!(A1 A2 A3 A4);
+wasm_types_tuple!(A1 A2 A3 A4 A5);
+wasm_types_tuple!(A1 A2 A3 A4 A5 A6);
+
+/// Host function result, errors trap the guest
+pub trait HostRet {
+    type Ok: WasmTypes;
+    fn into_result(self) -> Result<Self::Ok>;
+}
+macro_rules! host_ret {
+    ($($t:ty)*) => {$(
+        impl HostRet for $t {
+            type Ok = $t;
+            #[inline]
+            fn into_result(self) -> Result<$t> {
+                Ok(self)
+            }
+        }
+    )*};
+}
+host_ret!(() i32 u32 i64 u64);
+impl<T: HostRet> HostRet for Result<T> {
+    type Ok = T::Ok;
+    #[inline]
+    fn into_result(self) -> Result<T::Ok> {
+        self?.into_result()
+    }
+}
+
+/// Type erased host function called by backends
+pub type DynHostFunc<S> =
+    dyn Fn(&mut dyn Caller<S>, &[Value], &mut [Value]) -> Result<()> + Send + Sync;
+
+/// Function usable with [`Linker::add_func`]
+pub trait HostFunc<S, P, R>: Send + Sync + 'static {
+    fn signature() -> Signature;
+    fn into_dyn(self) -> Box<DynHostFunc<S>>;
+}
+macro_rules! host_func {
+    ($($a:ident)*) => {
+        impl<S, F, R, $($a,)*> HostFunc<S, ($($a,)*), R> for F
+        where
+            F: Fn(&mut dyn Caller<S>, $($a),*) -> R + Send + Sync + 'static,
+            R: HostRet,
+            $($a: WasmType,)*
+        {
+            #[inline]
+            fn signature() -> Signature {
+                Signature::of::<($($a,)*), R::Ok>()
+            }
+            #[allow(non_snake_case)]
+            fn into_dyn(self) -> Box<DynHostFunc<S>> {
+                Box::new(move |caller, params, results| {
+                    let ($($a,)*) = <($($a,)*)>::from_values(params)
+                        .ok_or_else(|| anyhow!("Invalid params"))?;
+                    self(caller, $($a),*).into_result()?.write_values(results);
+                    Ok(())
+                })
+            }
+        }
+    };
+}
+host_func!();
+host_func!(A1);
+host_func!(A1 A2);
+host_func!(A1 A2 A3);
+host_func!(A1 A2 A3 A4);
+host_func!(A1 A2 A3 A4 A5);
+host_func!(A1 A2 A3 A4 A5 A6);
+
+/// Host function context
+pub trait Caller<S> {
+    fn store(&self) -> &S;
+    fn store_mut(&mut self) -> &mut S;
+    /// Returns remaining fuel
+    fn consume_fuel(&mut self, v: u64) -> Result<u64>;
+    /// Exported linear memory along with store
+    fn memory(&mut self) -> Result<(&mut [u8], &mut S)>;
+}
+impl<'a, S: Store + 'a> dyn Caller<S> + 'a {
+    #[inline]
+    pub fn state(&self) -> &S::T {
+        self.store().state()
+    }
+    #[inline]
+    pub fn state_mut(&mut self) -> &mut S::T {
+        self.store_mut().state_mut()
+    }
+}
+
+/// Host functions and expected exports
+pub trait Linker<S: Store>: Sized {
+    type Engine;
+    type Template;
+
+    fn new(engine: &Self::Engine) -> Self;
+    fn add_dyn_func(
+        &mut self,
+        module: &str,
+        name: &str,
+        sig: Signature,
+        func: Box<DynHostFunc<S>>,
+    ) -> Result<&mut Self>;
+    #[inline]
+    fn add_func<P, R, F: HostFunc<S, P, R>>(
+        &mut self,
+        module: &str,
+        name: &str,
+        func: F,
+    ) -> Result<&mut Self> {
+        self.add_dyn_func(module, name, F::signature(), func.into_dyn())
+    }
+    fn add_export(&mut self, v: LinkExport) -> &mut Self;
+    /// Host functions defined so far
+    fn imports(&self) -> &Imports;
+
+    /// Compile module and validate it with linker
+    fn link(&self, bytes: &[u8]) -> Result<Self::Template>;
+}
+
+/// Exported function with static signature
+pub struct TypedFunc<F, P, R>(F, PhantomData<fn(P) -> R>);
+impl<F: Clone, P, R> Clone for TypedFunc<F, P, R> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), PhantomData)
+    }
+}
+impl<F, P, R> TypedFunc<F, P, R> {
+    #[inline]
+    pub fn raw(&self) -> &F {
+        &self.0
+    }
+}
+
+/// Running module
+pub trait Instance<S: Store>: Sized {
+    type Template;
+    type Func;
+
+    /// Instantiate, fails if module start function traps
+    fn new(tpl: &Self::Template, data: S::T, fuel: u64) -> Result<Self>;
+    /// Instantiate and call `_start` if exported
+    fn started(tpl: &Self::Template, data: S::T, fuel: u64) -> Result<(Self, Result<()>)> {
+        let mut i = Self::new(tpl, data, fuel)?;
+        let res = i.start();
+        Ok((i, res))
+    }
+    /// Call `_start` if exported
+    fn start(&mut self) -> Result<()> {
+        match self.get_func::<(), ()>(MAY_EXPORT_START.name) {
+            Ok(start) => self.call(&start, ()),
+            Err(_) => Ok(()),
+        }
+    }
+
+    fn store(&self) -> &S;
+    fn store_mut(&mut self) -> &mut S;
+    #[inline]
+    fn state<'a>(&'a self) -> &'a S::T
+    where
+        S: 'a,
+    {
+        self.store().state()
+    }
+    #[inline]
+    fn state_mut<'a>(&'a mut self) -> &'a mut S::T
+    where
+        S: 'a,
+    {
+        self.store_mut().state_mut()
+    }
+
+    /// Get exported function checking its signature
+    fn get_dyn_func(&mut self, name: &str, sig: &Signature) -> Result<Self::Func>;
+    fn call_dyn(&mut self, f: &Self::Func, params: &[Value], results: &mut [Value]) -> Result<()>;
+    #[inline]
+    fn get_func<P: WasmTypes, R: WasmTypes>(
+        &mut self,
+        name: &str,
+    ) -> Result<TypedFunc<Self::Func, P, R>> {
+        let f = self.get_dyn_func(name, &Signature::of::<P, R>())?;
+        Ok(TypedFunc(f, PhantomData))
+    }
+    #[inline]
+    fn call<P: WasmTypes, R: WasmTypes>(
+        &mut self,
+        f: &TypedFunc<Self::Func, P, R>,
+        p: P,
+    ) -> Result<R> {
+        let mut buf = [Value::I64(0); 2 * MAX_VALUES];
+        let (params, results) = buf.split_at_mut(MAX_VALUES);
+        let params = &mut params[..P::TYPES.len()];
+        p.write_values(params);
+        let results = &mut results[..R::TYPES.len()];
+        for (slot, ty) in results.iter_mut().zip(R::TYPES) {
+            *slot = Value::default_of(*ty);
+        }
+        self.call_dyn(&f.0, params, results)?;
+        R::from_values(results).ok_or_else(|| anyhow!("Invalid results"))
+    }
+
+    #[inline]
+    fn fuel(&mut self) -> u64 {
+        self.consume_fuel(0).unwrap_or(0)
+    }
+    fn add_fuel(&mut self, v: u64) -> Result<(), std::num::TryFromIntError>;
+    /// Returns remaining fuel
+    fn consume_fuel(&mut self, v: u64) -> Result<u64>;
+}
+
+/// Symbolicated call frame of a trap, innermost first
+#[derive(Clone, Debug, Default)]
+pub struct Frame {
+    /// Function index in module
+    pub func: u32,
+    /// Demangled function name from DWARF or name section
+    pub name: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+/// Demangle Rust symbols, other ones are kept as is
+pub fn demangle(name: &str) -> String {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(name) = rustc_demangle::try_demangle(name) {
+        return format!("{:#}", name);
+    }
+    name.to_owned()
+}
+
+#[derive(Debug)]
+pub struct MemoryOutOfBoundsError;
+impl fmt::Display for MemoryOutOfBoundsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("out of bounds memory access")
+    }
+}
+impl std::error::Error for MemoryOutOfBoundsError {}
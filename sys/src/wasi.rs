@@ -0,0 +1,296 @@
+//! Restricted and deterministic `wasi_snapshot_preview1` shared by backends
+//!
+//! Only stdout and stderr are writable, clocks are virtual and randomness is seeded.
+//! Everything else, like filesystem or sockets, is stubbed with an error code.
+use crate::log::{Level, LogBuffer, Record};
+use crate::rng::Rng;
+use crate::spec::wasm::{self as spec, MemoryOutOfBoundsError, Signature, ValType, Value};
+use anyhow::Result;
+use std::fmt;
+
+pub const MODULE: &str = "wasi_snapshot_preview1";
+
+const SUCCESS: i32 = 0;
+const EBADF: i32 = 8;
+const EINVAL: i32 = 28;
+const ENOSYS: i32 = 52;
+const ENOTSUP: i32 = 58;
+
+const CLOCK_REALTIME: u32 = 0;
+const CLOCK_MONOTONIC: u32 = 1;
+
+/// Fuel of each `fd_write` buffer and of each written byte, like `io.log`
+pub const WRITE_FUEL_BASE: u64 = 16;
+pub const WRITE_FUEL_RATIO: u64 = 2;
+/// Fuel of each `random_get` byte, like `sys.random`
+pub const RANDOM_FUEL_RATIO: u64 = 1;
+
+/// Configurable wasi behaviour of a store
+#[derive(Clone, Debug, Default)]
+pub struct WasiProfile {
+    /// Seed of `random_get`, disabled if `None`
+    pub seed: Option<u64>,
+    /// Duration of a tick in nanoseconds for virtual clocks, disabled if `None`
+    pub tick_ns: Option<u64>,
+}
+
+pub struct WasiStore<T> {
+    log: LogBuffer,
+    rng: Option<Rng>,
+    tick_ns: Option<u64>,
+    tick: u64,
+    state: T,
+}
+impl<T> spec::Store for WasiStore<T> {
+    type T = T;
+
+    fn new(state: T) -> Self {
+        Self {
+            log: LogBuffer::default(),
+            rng: None,
+            tick_ns: None,
+            tick: 0,
+            state,
+        }
+    }
+    #[inline]
+    fn state(&self) -> &T {
+        &self.state
+    }
+    #[inline]
+    fn state_mut(&mut self) -> &mut T {
+        &mut self.state
+    }
+}
+impl<T> WasiStore<T> {
+    /// Take buffered records
+    #[inline]
+    pub fn read_log(&mut self) -> Vec<Record> {
+        self.log.drain()
+    }
+    /// Append to a line based stream like stdout
+    #[inline]
+    pub fn write_log(&mut self, level: Level, v: &[u8]) {
+        self.log.write(level, v)
+    }
+    #[inline]
+    pub fn push_log(&mut self, record: Record) {
+        self.log.push(record)
+    }
+
+    /// Apply profile, resets random generator
+    pub fn set_profile(&mut self, profile: &WasiProfile) {
+        self.rng = profile.seed.map(Rng::new);
+        self.tick_ns = profile.tick_ns;
+    }
+    /// Move virtual clocks to the given tick
+    #[inline]
+    pub fn set_tick(&mut self, tick: u64) {
+        self.tick = tick;
+    }
+    fn now(&self) -> Option<u64> {
+        self.tick_ns.map(|ns| ns.saturating_mul(self.tick))
+    }
+}
+
+/// Module called `proc_exit`
+#[derive(Debug)]
+pub struct Exit(pub i32);
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Exited with code {}", self.0)
+    }
+}
+impl std::error::Error for Exit {}
+
+type Ctx<'a, T> = &'a mut dyn spec::Caller<WasiStore<T>>;
+
+/// Guest pointer arithmetic, out of bounds on overflow
+fn offset(ptr: u32, off: u32) -> Result<u32> {
+    Ok(ptr.checked_add(off).ok_or(MemoryOutOfBoundsError)?)
+}
+fn range(ptr: u32, len: u32) -> Result<std::ops::Range<usize>> {
+    let end = offset(ptr, len)?;
+    Ok(ptr as usize..end as usize)
+}
+fn get(mem: &[u8], ptr: u32, len: u32) -> Result<&[u8]> {
+    Ok(mem.get(range(ptr, len)?).ok_or(MemoryOutOfBoundsError)?)
+}
+fn get_u32(mem: &[u8], ptr: u32) -> Result<u32> {
+    Ok(u32::from_le_bytes(get(mem, ptr, 4)?.try_into().unwrap()))
+}
+fn get_mut(mem: &mut [u8], ptr: u32, len: u32) -> Result<&mut [u8]> {
+    Ok(mem
+        .get_mut(range(ptr, len)?)
+        .ok_or(MemoryOutOfBoundsError)?)
+}
+fn set(mem: &mut [u8], ptr: u32, v: &[u8]) -> Result<()> {
+    let len = u32::try_from(v.len()).map_err(|_| MemoryOutOfBoundsError)?;
+    get_mut(mem, ptr, len)?.copy_from_slice(v);
+    Ok(())
+}
+
+/// Unsupported functions by error code and params as `i` for i32 and `I` for i64
+const STUBS: &[(i32, &str, &str)] = &[
+    (EBADF, "fd_advise", "iIIi"),
+    (EBADF, "fd_allocate", "iII"),
+    (EBADF, "fd_close", "i"),
+    (EBADF, "fd_datasync", "i"),
+    (EBADF, "fd_fdstat_set_flags", "ii"),
+    (EBADF, "fd_fdstat_set_rights", "iII"),
+    (EBADF, "fd_filestat_get", "ii"),
+    (EBADF, "fd_filestat_set_size", "iI"),
+    (EBADF, "fd_filestat_set_times", "iIIi"),
+    (EBADF, "fd_pread", "iiiIi"),
+    (EBADF, "fd_prestat_get", "ii"),
+    (EBADF, "fd_prestat_dir_name", "iii"),
+    (EBADF, "fd_pwrite", "iiiIi"),
+    (EBADF, "fd_read", "iiii"),
+    (EBADF, "fd_readdir", "iiiIi"),
+    (EBADF, "fd_renumber", "ii"),
+    (EBADF, "fd_seek", "iIii"),
+    (EBADF, "fd_sync", "i"),
+    (EBADF, "fd_tell", "ii"),
+    (EBADF, "path_create_directory", "iii"),
+    (EBADF, "path_filestat_get", "iiiii"),
+    (EBADF, "path_filestat_set_times", "iiiiIIi"),
+    (EBADF, "path_link", "iiiiiii"),
+    (EBADF, "path_open", "iiiiiIIii"),
+    (EBADF, "path_readlink", "iiiiii"),
+    (EBADF, "path_remove_directory", "iii"),
+    (EBADF, "path_rename", "iiiiii"),
+    (EBADF, "path_symlink", "iiiii"),
+    (EBADF, "path_unlink_file", "iii"),
+    (ENOTSUP, "poll_oneoff", "iiii"),
+    (ENOSYS, "proc_raise", "i"),
+    (EBADF, "sock_accept", "iii"),
+    (EBADF, "sock_recv", "iiiiii"),
+    (EBADF, "sock_send", "iiiii"),
+    (EBADF, "sock_shutdown", "ii"),
+];
+
+pub fn add_to_linker<T: 'static, L: spec::Linker<WasiStore<T>>>(linker: &mut L) -> Result<()> {
+    // No arguments nor environment
+    for (sizes, get) in [
+        ("args_sizes_get", "args_get"),
+        ("environ_sizes_get", "environ_get"),
+    ] {
+        linker
+            .add_func(MODULE, sizes, |ctx: Ctx<T>, count: u32, size: u32| {
+                let (mem, _) = ctx.memory()?;
+                set(mem, count, &0u32.to_le_bytes())?;
+                set(mem, size, &0u32.to_le_bytes())?;
+                Ok(SUCCESS)
+            })?
+            .add_func(MODULE, get, |_: Ctx<T>, _: u32, _: u32| SUCCESS)?;
+    }
+
+    // Only stdout and stderr
+    linker
+        .add_func(
+            MODULE,
+            "fd_write",
+            |ctx: Ctx<T>, fd: u32, iovs: u32, iovs_len: u32, written: u32| {
+                if fd != 1 && fd != 2 {
+                    return Ok(EBADF);
+                }
+                let level = if fd == 1 { Level::Info } else { Level::Error };
+                let mut total = 0u32;
+                for i in 0..iovs_len {
+                    let iov = offset(iovs, i.checked_mul(8).ok_or(MemoryOutOfBoundsError)?)?;
+                    let mem = ctx.memory()?.0;
+                    let (ptr, len) = (get_u32(mem, iov)?, get_u32(mem, offset(iov, 4)?)?);
+                    ctx.consume_fuel(WRITE_FUEL_BASE + len as u64 * WRITE_FUEL_RATIO)?;
+                    let (mem, store) = ctx.memory()?;
+                    store.write_log(level, get(mem, ptr, len)?);
+                    total = total.wrapping_add(len);
+                }
+                set(ctx.memory()?.0, written, &total.to_le_bytes())?;
+                Ok(SUCCESS)
+            },
+        )?
+        .add_func(MODULE, "fd_fdstat_get", |ctx: Ctx<T>, fd: u32, ptr: u32| {
+            if fd != 1 && fd != 2 {
+                return Ok(EBADF);
+            }
+            const CHARACTER_DEVICE: u8 = 2;
+            const RIGHT_FD_WRITE: u64 = 1 << 6;
+            let mut stat = [0; 24];
+            stat[0] = CHARACTER_DEVICE;
+            stat[8..16].copy_from_slice(&RIGHT_FD_WRITE.to_le_bytes());
+            set(ctx.memory()?.0, ptr, &stat)?;
+            Ok(SUCCESS)
+        })?;
+
+    // Virtual clocks
+    linker
+        .add_func(MODULE, "clock_res_get", |ctx: Ctx<T>, id: u32, ptr: u32| {
+            let (mem, store) = ctx.memory()?;
+            let Some(ns) = store.tick_ns else {
+                return Ok(ENOSYS);
+            };
+            if id != CLOCK_REALTIME && id != CLOCK_MONOTONIC {
+                return Ok(EINVAL);
+            }
+            set(mem, ptr, &ns.to_le_bytes())?;
+            Ok(SUCCESS)
+        })?
+        .add_func(
+            MODULE,
+            "clock_time_get",
+            |ctx: Ctx<T>, id: u32, _precision: u64, ptr: u32| {
+                let (mem, store) = ctx.memory()?;
+                let Some(now) = store.now() else {
+                    return Ok(ENOSYS);
+                };
+                if id != CLOCK_REALTIME && id != CLOCK_MONOTONIC {
+                    return Ok(EINVAL);
+                }
+                set(mem, ptr, &now.to_le_bytes())?;
+                Ok(SUCCESS)
+            },
+        )?;
+
+    // Seeded randomness
+    linker.add_func(MODULE, "random_get", |ctx: Ctx<T>, ptr: u32, len: u32| {
+        if ctx.store().rng.is_none() {
+            return Ok(ENOSYS);
+        }
+        ctx.consume_fuel(len as u64 * RANDOM_FUEL_RATIO)?;
+        let (mem, store) = ctx.memory()?;
+        let Some(rng) = store.rng.as_mut() else {
+            return Ok(ENOSYS);
+        };
+        rng.fill(get_mut(mem, ptr, len)?);
+        Ok(SUCCESS)
+    })?;
+
+    linker
+        .add_func(MODULE, "sched_yield", |_: Ctx<T>| SUCCESS)?
+        .add_func(MODULE, "proc_exit", |_: Ctx<T>, code: i32| -> Result<()> {
+            Err(Exit(code).into())
+        })?;
+
+    for (errno, name, params) in STUBS.iter().copied() {
+        let sig = Signature {
+            params: params
+                .chars()
+                .map(|c| match c {
+                    'I' => ValType::I64,
+                    _ => ValType::I32,
+                })
+                .collect(),
+            results: vec![ValType::I32],
+        };
+        linker.add_dyn_func(
+            MODULE,
+            name,
+            sig,
+            Box::new(move |_, _, results| {
+                results[0] = Value::I32(errno);
+                Ok(())
+            }),
+        )?;
+    }
+    Ok(())
+}
@@ -1 +1,3 @@
-todo!();
+#[cfg(not(feature = "interpreter"))]
+compile_error!("wasm32 target requires the interpreter feature");
+pub use crate::interpreter::wasm;
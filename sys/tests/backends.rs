@@ -0,0 +1,205 @@
+//! Same modules on interpreter and native backends
+#![cfg(all(feature = "interpreter", not(target_arch = "wasm32")))]
+
+/// Observable effects of a call
+#[derive(Debug)]
+struct Run {
+    /// Returned value or trap message
+    res: Result<i64, String>,
+    /// Values passed to host `log`
+    logs: Vec<i64>,
+    /// Fuel remaining after the call
+    fuel: u64,
+}
+
+macro_rules! backend {
+    ($name:ident, $wasm:path) => {
+        mod $name {
+            use wasm::spec::{Caller, Instance as _, Linker as _};
+            use $wasm as wasm;
+
+            type S = wasm::RawStore<Vec<i64>>;
+
+            /// Instantiate without imports, running start function
+            pub fn instantiate(wat: &str, fuel: u64) -> Result<(), String> {
+                let linker = wasm::Linker::<S>::new(&wasm::Engine::new());
+                let tpl = linker.link(wat.as_bytes()).unwrap();
+                wasm::Instance::<S>::new(&tpl, Vec::new(), fuel)
+                    .map(|_| ())
+                    .map_err(|err| format!("{:#}", err))
+            }
+
+            pub fn run(wat: &str, arg: i64, fuel: u64) -> super::Run {
+                let mut linker = wasm::Linker::<S>::new(&wasm::Engine::new());
+                linker
+                    .add_func("env", "log", |c: &mut dyn Caller<S>, v: i64| {
+                        c.state_mut().push(v)
+                    })
+                    .unwrap()
+                    .add_func("env", "burn", |c: &mut dyn Caller<S>, v: i64| {
+                        c.consume_fuel(v as u64).map(|left| left as i64)
+                    })
+                    .unwrap()
+                    .add_func("env", "fail", |_: &mut dyn Caller<S>, v: i32| {
+                        anyhow::ensure!(v == 0, "host failure {}", v);
+                        Ok(())
+                    })
+                    .unwrap()
+                    .add_func("env", "sum", |c: &mut dyn Caller<S>, ptr: i32, len: i32| {
+                        let (mem, _) = c.memory()?;
+                        let bytes = mem
+                            .get(ptr as usize..(ptr + len) as usize)
+                            .ok_or(wasm::MemoryOutOfBoundsError)?;
+                        Ok(bytes.iter().map(|b| *b as i64).sum::<i64>())
+                    })
+                    .unwrap();
+                let tpl = linker.link(wat.as_bytes()).unwrap();
+                let mut i = wasm::Instance::<S>::new(&tpl, Vec::new(), fuel).unwrap();
+                let f = i.get_func::<i64, i64>("run").unwrap();
+                let res = i.call(&f, arg).map_err(|err| format!("{:#}", err));
+                super::Run {
+                    res,
+                    logs: i.state().clone(),
+                    fuel: i.fuel(),
+                }
+            }
+        }
+    };
+}
+backend!(native, scalliony_sys::wasm);
+backend!(interpreter, scalliony_sys::interpreter::wasm);
+
+const FUEL: u64 = 100_000;
+
+/// Run on both backends and check they agree on results and host calls
+fn both(wat: &str, arg: i64, fuel: u64) -> (Run, Run) {
+    let n = native::run(wat, arg, fuel);
+    let i = interpreter::run(wat, arg, fuel);
+    assert_eq!(n.logs, i.logs, "host calls differ");
+    match (&n.res, &i.res) {
+        (Ok(a), Ok(b)) => assert_eq!(a, b, "results differ"),
+        (Err(_), Err(_)) => {}
+        _ => panic!("only one backend trapped: {:?} {:?}", n.res, i.res),
+    }
+    (n, i)
+}
+
+const LOOP: &str = r#"(module
+  (import "env" "log" (func $log (param i64)))
+  (func (export "run") (param $n i64) (result i64)
+    (local $acc i64)
+    (block $done
+      (loop $next
+        (br_if $done (i64.eqz (local.get $n)))
+        (call $log (local.get $n))
+        (local.set $acc (i64.add (local.get $acc) (i64.mul (local.get $n) (local.get $n))))
+        (local.set $n (i64.sub (local.get $n) (i64.const 1)))
+        (br $next)))
+    (local.get $acc)))"#;
+
+#[test]
+fn same_results_and_host_calls() {
+    for arg in [0, 1, 7, 50] {
+        let (n, i) = both(LOOP, arg, FUEL);
+        assert_eq!(n.res, Ok((1..=arg).map(|v| v * v).sum()));
+        assert_eq!(n.logs, (1..=arg).rev().collect::<Vec<_>>());
+        assert!(n.fuel < FUEL && i.fuel < FUEL);
+    }
+}
+
+#[test]
+fn fuel_grows_with_work() {
+    for backend in [native::run, interpreter::run] {
+        let used = |arg| FUEL - backend(LOOP, arg, FUEL).fuel;
+        assert!(used(1) < used(10));
+        assert!(used(10) < used(100));
+    }
+}
+
+#[test]
+fn both_run_out_of_fuel() {
+    // Instruction costs differ, so only the interrupted work is comparable.
+    // The interpreter charges whole blocks upfront and may leave some fuel
+    for backend in [native::run, interpreter::run] {
+        let run = backend(LOOP, i64::MAX, 1_000);
+        assert!(run.res.is_err());
+        assert!(run.fuel < 32);
+        assert!(!run.logs.is_empty());
+        assert!(run
+            .logs
+            .iter()
+            .rev()
+            .copied()
+            .eq(i64::MAX - run.logs.len() as i64 + 1..=i64::MAX));
+    }
+}
+
+#[test]
+fn host_fuel_is_charged_exactly() {
+    const BURN: &str = r#"(module
+      (import "env" "burn" (func $burn (param i64) (result i64)))
+      (func (export "run") (param $n i64) (result i64)
+        (call $burn (local.get $n))))"#;
+    for backend in [native::run, interpreter::run] {
+        let base = backend(BURN, 0, FUEL);
+        let run = backend(BURN, 1_000, FUEL);
+        assert_eq!(base.fuel - run.fuel, 1_000);
+        assert_eq!(run.res, Ok(run.fuel as i64));
+        assert!(backend(BURN, FUEL as i64 * 2, FUEL).res.is_err());
+    }
+}
+
+#[test]
+fn same_traps() {
+    const TRAPS: &str = r#"(module
+      (import "env" "log" (func $log (param i64)))
+      (import "env" "fail" (func $fail (param i32)))
+      (func (export "run") (param $n i64) (result i64)
+        (call $log (local.get $n))
+        (if (i64.eq (local.get $n) (i64.const 1)) (then unreachable))
+        (if (i64.eq (local.get $n) (i64.const 2))
+          (then (drop (i64.div_s (i64.const 1) (i64.const 0)))))
+        (call $fail (i32.wrap_i64 (local.get $n)))
+        (i64.const 42)))"#;
+    let (n, _) = both(TRAPS, 0, FUEL);
+    assert_eq!(n.res, Ok(42));
+    for arg in [1, 2] {
+        both(TRAPS, arg, FUEL);
+    }
+    let (n, i) = both(TRAPS, 3, FUEL);
+    for res in [n.res, i.res] {
+        assert!(res.unwrap_err().contains("host failure 3"));
+    }
+}
+
+#[test]
+fn same_memory_access() {
+    const MEM: &str = r#"(module
+      (import "env" "sum" (func $sum (param i32 i32) (result i64)))
+      (memory (export "memory") 1)
+      (data (i32.const 16) "\01\02\03\04\05")
+      (func (export "run") (param $n i64) (result i64)
+        (call $sum (i32.const 16) (i32.wrap_i64 (local.get $n)))))"#;
+    let (n, _) = both(MEM, 5, FUEL);
+    assert_eq!(n.res, Ok(15));
+    let (n, i) = both(MEM, 0x10000, FUEL);
+    assert!(n.res.is_err() && i.res.is_err());
+}
+
+#[test]
+fn start_failure_is_an_error() {
+    const START: &str = r#"(module
+      (func $start (loop $l (br $l)))
+      (start $start))"#;
+    const TRAP: &str = r#"(module
+      (func $start unreachable)
+      (start $start))"#;
+    const OK: &str = r#"(module
+      (func $start nop)
+      (start $start))"#;
+    for instantiate in [native::instantiate, interpreter::instantiate] {
+        assert!(instantiate(START, 1_000).is_err());
+        assert!(instantiate(TRAP, FUEL).is_err());
+        assert_eq!(instantiate(OK, FUEL), Ok(()));
+    }
+}
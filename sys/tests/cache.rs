@@ -0,0 +1,51 @@
+//! Precompiled modules stored on disk
+#![cfg(not(target_arch = "wasm32"))]
+
+use scalliony_sys::wasm::ModuleCache;
+use std::{fs, path::PathBuf};
+
+const A: &str = r#"(module (func (export "a")))"#;
+const B: &str = r#"(module (func (export "b")))"#;
+
+fn exports(module: &wasmtime::Module) -> Vec<&str> {
+    module.exports().map(|e| e.name()).collect()
+}
+/// Only file of the cache directory
+fn cached(dir: &PathBuf) -> PathBuf {
+    let files: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 1, "{:?}", files);
+    files[0].clone()
+}
+
+#[test]
+fn modules_are_cached_by_code() {
+    let dir = std::env::temp_dir().join(format!("scalliony-cache-{}", std::process::id()));
+    _ = fs::remove_dir_all(&dir);
+    let engine = wasmtime::Engine::default();
+    let cache = ModuleCache::new(&dir, &engine).unwrap();
+
+    let module = cache.get_or_compile(&engine, A.as_bytes()).unwrap();
+    assert_eq!(exports(&module), ["a"]);
+    let path = cached(&dir);
+
+    // Second compile loads the stored file, here swapped with another module
+    let other = wasmtime::Module::new(&engine, B).unwrap();
+    fs::write(&path, other.serialize().unwrap()).unwrap();
+    let module = cache.get_or_compile(&engine, A.as_bytes()).unwrap();
+    assert_eq!(exports(&module), ["b"]);
+
+    // Truncated file is compiled again and replaced
+    let len = fs::metadata(&path).unwrap().len();
+    fs::write(&path, &fs::read(&path).unwrap()[..len as usize / 2]).unwrap();
+    let module = cache.get_or_compile(&engine, A.as_bytes()).unwrap();
+    assert_eq!(exports(&module), ["a"]);
+    assert_eq!(cached(&dir), path);
+    let module = cache.get_or_compile(&engine, A.as_bytes()).unwrap();
+    assert_eq!(exports(&module), ["a"]);
+    assert!(fs::metadata(&path).unwrap().len() > len / 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
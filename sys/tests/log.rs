@@ -0,0 +1,50 @@
+//! Line splitting and capacity of log buffers
+
+use scalliony_sys::log::{Level, LogBuffer, OVERHEAD};
+
+fn msgs(buf: &mut LogBuffer) -> Vec<String> {
+    buf.drain().into_iter().map(|r| r.msg).collect()
+}
+
+#[test]
+fn lines_across_writes() {
+    let mut buf = LogBuffer::new(1024);
+    buf.write(Level::Info, b"he");
+    buf.write(Level::Info, b"llo\nwor");
+    buf.write(Level::Error, b"oops\n");
+    buf.write(Level::Info, b"ld\n\nlast");
+    assert_eq!(msgs(&mut buf), ["hello", "oops", "world", "", "last"]);
+    assert!(msgs(&mut buf).is_empty());
+}
+
+#[test]
+fn many_lines_keep_the_last_ones() {
+    const CAP: usize = 256;
+    let mut buf = LogBuffer::new(CAP);
+    buf.write(Level::Info, b"partial ");
+    let lines = 1_000_000;
+    let mut big = Vec::new();
+    for i in 0..lines {
+        big.extend_from_slice(format!("{}\n", i).as_bytes());
+    }
+    buf.write(Level::Info, &big);
+    let records = buf.drain();
+    let kept = records.len() - 1;
+    assert!(kept <= CAP / OVERHEAD);
+    assert_eq!(records[0].level, Level::Warn);
+    assert_eq!(records[0].msg, format!("{} messages dropped", lines - kept));
+    let last: Vec<String> = (lines - kept..lines).map(|i| i.to_string()).collect();
+    let msgs: Vec<&String> = records[1..].iter().map(|r| &r.msg).collect();
+    assert_eq!(msgs, last.iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn long_line_is_truncated() {
+    const CAP: usize = 128;
+    let mut buf = LogBuffer::new(CAP);
+    buf.write(Level::Info, &[b'a'; 10 * CAP]);
+    assert_eq!(msgs(&mut buf), ["a".repeat(CAP - OVERHEAD)]);
+    // Rest of the long line is not carried over
+    buf.write(Level::Info, b"c\n");
+    assert_eq!(msgs(&mut buf), ["c"]);
+}
@@ -0,0 +1,81 @@
+//! Guest controlled inputs of wasi functions
+#![cfg(not(target_arch = "wasm32"))]
+
+use scalliony_sys::wasi::{self, WasiProfile, WasiStore};
+use scalliony_sys::wasm::{self, spec::Instance as _, spec::Linker as _};
+
+type S = WasiStore<()>;
+const FUEL: u64 = 100_000;
+
+/// Run `run` export with given params, returns errno or trap and used fuel
+fn run(body: &str, params: &str, args: &[i32]) -> (Result<i32, String>, u64, Vec<String>) {
+    let wat = format!(
+        r#"(module
+          (import "{m}" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+          (import "{m}" "random_get" (func $random_get (param i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "\10\00\00\00\05\00\00\00")
+          (data (i32.const 16) "hello\n")
+          (func (export "run") {params} (result i32) {body}))"#,
+        m = wasi::MODULE,
+    );
+    let mut linker = wasm::Linker::<S>::new(&wasm::Engine::new());
+    linker.add_wasi();
+    let tpl = linker.link(wat.as_bytes()).unwrap();
+    let mut i = wasm::Instance::<S>::new(&tpl, (), FUEL).unwrap();
+    i.store_mut().set_profile(&WasiProfile {
+        seed: Some(1),
+        tick_ns: None,
+    });
+    let before = i.fuel();
+    let res = match args {
+        [] => i.get_func::<(), i32>("run").and_then(|f| i.call(&f, ())),
+        [a] => i.get_func::<i32, i32>("run").and_then(|f| i.call(&f, *a)),
+        [a, b] => (i.get_func::<(i32, i32), i32>("run")).and_then(|f| i.call(&f, (*a, *b))),
+        _ => unreachable!(),
+    };
+    let used = before - i.fuel();
+    let logs = i
+        .store_mut()
+        .read_log()
+        .into_iter()
+        .map(|r| r.msg)
+        .collect();
+    (res.map_err(|err| format!("{:#}", err)), used, logs)
+}
+
+const WRITE: &str = "(call $fd_write (i32.const 1) (local.get 0) (local.get 1) (i32.const 64))";
+
+#[test]
+fn fd_write_charges_per_byte() {
+    let (res, one, logs) = run(WRITE, "(param i32 i32)", &[0, 1]);
+    assert_eq!(res, Ok(0));
+    assert_eq!(logs, ["hello"]);
+    let (_, none, _) = run(WRITE, "(param i32 i32)", &[0, 0]);
+    assert!(one - none >= wasi::WRITE_FUEL_BASE + 5 * wasi::WRITE_FUEL_RATIO);
+}
+
+#[test]
+fn fd_write_rejects_overflowing_pointers() {
+    for (iovs, len) in [(-8, 2), (-4, 1), (0, -1), (-16, 4)] {
+        let (res, _, _) = run(WRITE, "(param i32 i32)", &[iovs, len]);
+        assert!(res.is_err(), "{} {}", iovs, len);
+    }
+    // Huge buffer list runs out of fuel instead of looping for free
+    let wat = "(i32.store (i32.const 32) (i32.const 0)) \
+        (i32.store (i32.const 36) (i32.const 0)) \
+        (call $fd_write (i32.const 1) (i32.const 32) (i32.const 0x1ff0) (i32.const 64))";
+    let (res, used, _) = run(wat, "", &[]);
+    assert!(res.is_err());
+    assert!(used >= FUEL - wasi::WRITE_FUEL_BASE);
+}
+
+#[test]
+fn random_get_charges_per_byte() {
+    let get = "(call $random_get (i32.const 128) (local.get 0))";
+    let (res, small, _) = run(get, "(param i32)", &[8]);
+    assert_eq!(res, Ok(0));
+    let (_, big, _) = run(get, "(param i32)", &[1008]);
+    assert_eq!(big - small, 1000 * wasi::RANDOM_FUEL_RATIO);
+    assert!(run(get, "(param i32)", &[-1]).0.is_err());
+}